@@ -7,14 +7,123 @@
     or randomness) can be considered special cases of this trait.
 */
 
-use super::player::Player;
+use super::console::Console;
+use super::player::{Player, PlayerSet};
+use super::random::{self, GameRng};
+
+use std::fmt::{self, Display};
 
 /// Type to indicate the state of a game (whether in progress or ended)
 /// N is the number of players
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum GameStatus<const N: usize> {
+    /// In progress: the player to move
     ToMove(Player<N>),
+    /// In progress: the players in the set each choose a move without
+    /// seeing the others' choices, and the moves are made together
+    Simultaneous(PlayerSet<N>),
+    /// In progress: a random event is to happen (a move by "nature",
+    /// drawn from AbstractGame::chance_outcomes)
+    Chance,
+    /// Ended with a single winner
     Won(Player<N>),
+    /// Ended with no winner
+    Draw,
+    /// Ended with several players winning together (e.g. a team)
+    SharedWin(PlayerSet<N>),
+    /// Ended with the players ranked: entry i is the place of player i,
+    /// where 0 is first place. Tied players share a place.
+    Ranked([usize; N]),
+}
+impl<const N: usize> GameStatus<N> {
+    /// Whether the status is a final outcome
+    pub fn is_ended(&self) -> bool {
+        !matches!(
+            self,
+            GameStatus::ToMove(_)
+                | GameStatus::Simultaneous(_)
+                | GameStatus::Chance
+        )
+    }
+
+    /// Whether a player is (one of the players) to move
+    pub fn is_to_move(&self, plyr: Player<N>) -> bool {
+        match *self {
+            GameStatus::ToMove(to_move) => to_move == plyr,
+            GameStatus::Simultaneous(plyrs) => plyrs.contains(plyr),
+            _ => false,
+        }
+    }
+
+    /// Players who won: the single winner, the players sharing a win, or
+    /// the players in first place. Empty if not ended or a draw.
+    pub fn winners(&self) -> PlayerSet<N> {
+        match *self {
+            GameStatus::ToMove(_)
+            | GameStatus::Simultaneous(_)
+            | GameStatus::Chance
+            | GameStatus::Draw => PlayerSet::empty(),
+            GameStatus::Won(plyr) => std::iter::once(plyr).collect(),
+            GameStatus::SharedWin(plyrs) => plyrs,
+            GameStatus::Ranked(places) => {
+                Player::all().filter(|p| places[p.as_index()] == 0).collect()
+            }
+        }
+    }
+
+    /// Default payoffs for an ended game (None if not ended), between 0
+    /// and 1 for each player:
+    /// - a single winner gets 1 and everyone else 0;
+    /// - players sharing a win split 1 evenly, as do all players in a draw;
+    /// - in a ranking, the fraction of the other players you finished
+    ///   ahead of, with ties counting half.
+    pub fn payoffs(&self) -> Option<[f64; N]> {
+        let mut result = [0.0; N];
+        match *self {
+            GameStatus::ToMove(_)
+            | GameStatus::Simultaneous(_)
+            | GameStatus::Chance => return None,
+            GameStatus::Won(plyr) => result[plyr.as_index()] = 1.0,
+            GameStatus::Draw => result = [1.0 / N as f64; N],
+            GameStatus::SharedWin(plyrs) => {
+                for plyr in plyrs.iter() {
+                    result[plyr.as_index()] = 1.0 / plyrs.len() as f64;
+                }
+            }
+            GameStatus::Ranked(places) => {
+                let others = N.saturating_sub(1).max(1) as f64;
+                for (i, &place) in places.iter().enumerate() {
+                    let ahead = places.iter().filter(|&&p| p > place).count();
+                    let tied = places.iter().filter(|&&p| p == place).count();
+                    result[i] =
+                        (ahead as f64 + (tied - 1) as f64 / 2.0) / others;
+                }
+            }
+        }
+        Some(result)
+    }
+}
+
+/// One step of a game: a single move (by a player, or by chance), or the
+/// moves of several players made simultaneously
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Turn<M, const N: usize> {
+    Move(M),
+    Simultaneous(Vec<(Player<N>, M)>),
+}
+impl<M: Display, const N: usize> Display for Turn<M, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Turn::Move(mv) => write!(f, "{}", mv),
+            Turn::Simultaneous(moves) => {
+                let moves: Vec<String> = moves
+                    .iter()
+                    .map(|(plyr, mv)| format!("{}: {}", plyr, mv))
+                    .collect();
+                write!(f, "{}", moves.join("; "))
+            }
+        }
+    }
 }
 
 /// Main game trait
@@ -23,8 +132,14 @@ pub enum GameStatus<const N: usize> {
 /// if N = 0 is that it's impossible to implement `fn status()` since GameStatus
 /// is uninhabited.
 pub trait AbstractGame<const N: usize> {
+    /// Short name of the game (used to label saved games)
+    const NAME: &'static str;
+
     type Move: Eq;
     type SetupParams;
+    /// What a player knows about the game. For games with complete
+    /// information, this can simply be the game itself.
+    type Observation;
 
     /*
         Provided methods
@@ -32,18 +147,34 @@ pub trait AbstractGame<const N: usize> {
 
     /// Get setup parameters from user input
     /// This function should call the util::from_user_input family of functions
-    fn setup_from_user_input() -> Self::SetupParams;
+    fn setup_from_user_input(console: &mut dyn Console) -> Self::SetupParams;
+
+    /// Setup parameters as a single line of text (for saving games)
+    fn format_params(params: &Self::SetupParams) -> String;
+
+    /// Parse setup parameters in the format of format_params
+    fn parse_params(raw: &str) -> Result<Self::SetupParams, String>;
 
     /// Starting position
-    fn game_setup(params: Self::SetupParams) -> Self;
+    /// Any randomness in the starting position should be drawn from rng.
+    fn game_setup(params: Self::SetupParams, rng: &mut GameRng) -> Self;
 
-    /// Who is to move, or (if the game is ended) who has won
+    /// Who is to move, or (if the game is ended) the outcome
     fn status(&self) -> GameStatus<N>;
 
+    /// At a chance node (status Chance), the possible moves of nature with
+    /// their probabilities.
+    /// Only games with randomness need to implement this.
+    fn chance_outcomes(&self) -> Vec<(Self::Move, f64)> {
+        Vec::new()
+    }
+
     /// Query to the user to make a move
     fn query(&self) -> String;
 
     /// Parsing the move from a string.
+    /// The Display format of moves (if any) should be accepted, so that
+    /// moves can be saved and loaded.
     /// On error, print a helpful error message.
     /// This method should only check for whether the string can be parsed
     /// as Move; additionally checking that the move is valid should be in
@@ -56,23 +187,71 @@ pub trait AbstractGame<const N: usize> {
     /// is_valid_move is derived from this.
     fn check_move(&self, mv: &Self::Move) -> Result<(), String>;
 
+    /// All valid moves in the current position.
+    /// Should agree with check_move: every move returned passes check_move,
+    /// and every move passing check_move is returned.
+    /// At a chance node, these are the possible outcomes.
+    /// Empty if the game has ended.
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
     /// Making the move -- ok to assume that it is valid
     fn make_move(&mut self, mv: Self::Move);
 
+    /// In a simultaneous-move position, the valid moves for one of the
+    /// players to move. By default the same as legal_moves, which is right
+    /// when all players to move have the same options.
+    fn legal_moves_for(&self, _plyr: Player<N>) -> Vec<Self::Move> {
+        self.legal_moves()
+    }
+
+    /// In a simultaneous-move position, check that the move is valid for
+    /// one of the players to move. By default the same as check_move.
+    fn check_move_for(
+        &self,
+        _plyr: Player<N>,
+        mv: &Self::Move,
+    ) -> Result<(), String> {
+        self.check_move(mv)
+    }
+
+    /// In a simultaneous-move position, make the moves of all the players
+    /// to move together -- ok to assume that they are valid.
+    /// Only games with simultaneous moves need to implement this.
+    fn make_simultaneous_moves(
+        &mut self,
+        _moves: Vec<(Player<N>, Self::Move)>,
+    ) {
+        panic!("this game has no simultaneous moves")
+    }
+
     /// Game state visible to a particular player
     fn print_state_visible(&self, plyr: Player<N>) -> String;
 
+    /// Game state visible to a particular player, as data.
+    /// This is all that AIs get to see, so it must not include anything
+    /// hidden from that player.
+    fn observe(&self, plyr: Player<N>) -> Self::Observation;
+
     /*
         Derived functionality
     */
 
     /// Create a new game using setup paramaters from user input
-    fn new_from_user_input() -> Self
+    fn new_from_user_input(console: &mut dyn Console, rng: &mut GameRng) -> Self
     where
         Self: Sized,
     {
-        let params = Self::setup_from_user_input();
-        Self::game_setup(params)
+        let params = Self::setup_from_user_input(console);
+        Self::game_setup(params, rng)
+    }
+
+    /// At a chance node, draw the move of nature
+    fn sample_chance_move(&self, rng: &mut GameRng) -> Self::Move
+    where
+        Self::Move: Clone,
+    {
+        debug_assert_eq!(self.status(), GameStatus::Chance);
+        random::sample(&self.chance_outcomes(), rng).clone()
     }
 
     /// Given a move, return whether or not it is valid
@@ -86,6 +265,39 @@ pub trait AbstractGame<const N: usize> {
         self.parse_move(raw).and_then(|mv| self.check_move(&mv).map(|()| mv))
     }
 
+    /// Parse a move and check it is valid for a particular player.
+    /// Same as parse_valid_move, except in simultaneous-move positions.
+    fn parse_valid_move_for(
+        &self,
+        plyr: Player<N>,
+        raw: &str,
+    ) -> Result<Self::Move, String> {
+        self.parse_move(raw)
+            .and_then(|mv| self.check_move_for(plyr, &mv).map(|()| mv))
+    }
+
+    /// Make a turn: either a single move, or simultaneous moves
+    fn make_turn(&mut self, turn: Turn<Self::Move, N>) {
+        match turn {
+            Turn::Move(mv) => self.make_move(mv),
+            Turn::Simultaneous(moves) => self.make_simultaneous_moves(moves),
+        }
+    }
+
+    /// Whether a move is enumerated by legal_moves
+    fn is_legal_move(&self, mv: &Self::Move) -> bool {
+        self.legal_moves().contains(mv)
+    }
+
+    /// Invariant check (intended for debug assertions) that legal_moves
+    /// agrees with check_move: all enumerated moves are valid, and `mv` is
+    /// enumerated exactly when it is valid.
+    fn legal_moves_agree(&self, mv: &Self::Move) -> bool {
+        let legal = self.legal_moves();
+        legal.iter().all(|m| self.is_valid_move(m))
+            && legal.contains(mv) == self.is_valid_move(mv)
+    }
+
     /// Number of players in the game
     fn num_players(&self) -> usize {
         N
@@ -93,17 +305,23 @@ pub trait AbstractGame<const N: usize> {
 
     /// Whether the game has ended
     fn is_ended(&self) -> bool {
-        match self.status() {
-            GameStatus::ToMove(_) => false,
-            GameStatus::Won(_) => true,
-        }
+        self.status().is_ended()
     }
 
-    /// Current player (if not ended)
+    /// Payoff for each player once the game has ended (None if not ended).
+    /// Games with scores (or where the margin of victory matters) should
+    /// override this; the default is derived from the status (see
+    /// GameStatus::payoffs).
+    fn payoffs(&self) -> Option<[f64; N]> {
+        self.status().payoffs()
+    }
+
+    /// Current player (if not ended, and not a chance node or a
+    /// simultaneous-move position)
     fn cur_player(&self) -> Option<Player<N>> {
         match self.status() {
             GameStatus::ToMove(plyr) => Some(plyr),
-            GameStatus::Won(_) => None,
+            _ => None,
         }
     }
 }
@@ -115,8 +333,56 @@ where
     /// Initialize
     fn new() -> Self;
 
-    /// Given a state, choose a valid move
-    /// This must satisfy: (1) only uses information that is
-    /// be available to that player; (2) the returned move should be valid
-    fn ai_move(&mut self, game: &G, plyr: Player<N>) -> G::Move;
+    /// Given what plyr can observe of the game, and the valid moves,
+    /// choose one of the valid moves.
+    /// Only called when plyr is to move, possibly simultaneously with other
+    /// players (never at chance nodes); any randomness in the choice should
+    /// be drawn from rng.
+    fn ai_move(
+        &mut self,
+        obs: &G::Observation,
+        plyr: Player<N>,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plyr(i: usize) -> Player<3> {
+        Player::from_index(i).unwrap()
+    }
+
+    #[test]
+    fn test_winners() {
+        assert!(GameStatus::ToMove(plyr(0)).winners().is_empty());
+        assert!(GameStatus::<3>::Draw.winners().is_empty());
+        assert_eq!(GameStatus::Won(plyr(1)).winners().len(), 1);
+        let ranked = GameStatus::Ranked([1, 0, 0]);
+        let winners = ranked.winners();
+        assert!(!winners.contains(plyr(0)));
+        assert!(winners.contains(plyr(1)) && winners.contains(plyr(2)));
+    }
+
+    #[test]
+    fn test_payoffs() {
+        assert_eq!(GameStatus::ToMove(plyr(0)).payoffs(), None);
+        assert_eq!(GameStatus::Won(plyr(2)).payoffs(), Some([0.0, 0.0, 1.0]));
+        assert_eq!(GameStatus::<2>::Draw.payoffs(), Some([0.5, 0.5]));
+        let shared = [plyr(0), plyr(1)].iter().copied().collect();
+        assert_eq!(
+            GameStatus::SharedWin(shared).payoffs(),
+            Some([0.5, 0.5, 0.0])
+        );
+        assert_eq!(
+            GameStatus::Ranked([2, 0, 1]).payoffs(),
+            Some([0.0, 1.0, 0.5])
+        );
+        assert_eq!(
+            GameStatus::Ranked([0, 0, 1]).payoffs(),
+            Some([0.75, 0.75, 0.0])
+        );
+    }
 }
@@ -0,0 +1,108 @@
+/*
+    Information set Monte Carlo tree search
+
+    In a game with hidden information, an AI only sees its observation of
+    the game, not the game itself. Before each iteration of the tree search
+    (see mcts.rs), this AI guesses the hidden information: it draws a whole
+    game consistent with what it has observed (a determinization), and
+    walks down the tree in that game. The tree is shared between all the
+    guesses, so a move is judged by how it does across them, and moves
+    that are only legal in some guesses are judged against how often they
+    were available.
+
+    This works for any game that can draw a hidden state consistent with an
+    observation (the Determinize trait).
+*/
+
+use super::mcts::{Budget, MctsAi};
+use crate::abstract_game::{AbstractGame, Ai};
+use crate::player::Player;
+use crate::random::GameRng;
+
+use rand::seq::SliceRandom;
+
+/// A game that can guess its hidden information from a player's
+/// observation
+pub trait Determinize<const N: usize>: AbstractGame<N> + Sized {
+    /// A game, drawn at random, in which plyr would make this observation.
+    /// Only called when plyr is to move.
+    fn determinize(
+        obs: &Self::Observation,
+        plyr: Player<N>,
+        rng: &mut GameRng,
+    ) -> Self;
+}
+
+/// AI that searches over guesses of the hidden information
+pub struct IsmctsAi<G, const N: usize> {
+    mcts: MctsAi<G, N>,
+}
+
+impl<G, const N: usize> IsmctsAi<G, N>
+where
+    G: Determinize<N> + Clone,
+    G::Move: Clone,
+{
+    pub fn with_options(budget: Budget, exploration: f64) -> Self {
+        Self { mcts: MctsAi::with_options(budget, exploration) }
+    }
+}
+
+impl<G, const N: usize> Ai<G, N> for IsmctsAi<G, N>
+where
+    G: Determinize<N> + Clone,
+    G::Move: Clone,
+{
+    fn new() -> Self {
+        Self::with_options(
+            Budget::Iterations(MctsAi::<G, N>::DEFAULT_ITERATIONS),
+            MctsAi::<G, N>::DEFAULT_EXPLORATION,
+        )
+    }
+    fn ai_move(
+        &mut self,
+        obs: &G::Observation,
+        plyr: Player<N>,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move {
+        let first = G::determinize(obs, plyr, rng);
+        if first.cur_player() != Some(plyr) {
+            // Simultaneous moves: there is no tree to search
+            return legal_moves
+                .choose(rng)
+                .expect("there should be a legal move")
+                .clone();
+        }
+        let mut first = Some(first);
+        let sample = |rng: &mut GameRng| match first.take() {
+            Some(game) => game,
+            None => G::determinize(obs, plyr, rng),
+        };
+        self.mcts.search(sample, legal_moves, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battleship::game::{BattleshipParams, GameState};
+    use crate::random;
+
+    #[test]
+    fn test_plays_battleship() {
+        let mut rng = random::seeded(0);
+        let mut game =
+            GameState::game_setup(BattleshipParams::default(), &mut rng);
+        let mut ai =
+            IsmctsAi::<GameState, 2>::with_options(Budget::Iterations(20), 1.0);
+        for _ in 0..10 {
+            let plyr = game.cur_player().unwrap();
+            let obs = game.observe(plyr);
+            let legal_moves = game.legal_moves();
+            let mv = ai.ai_move(&obs, plyr, &legal_moves, &mut rng);
+            assert!(game.is_valid_move(&mv));
+            game.make_move(mv);
+        }
+    }
+}
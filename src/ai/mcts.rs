@@ -0,0 +1,425 @@
+/*
+    Monte Carlo tree search (UCT)
+
+    The AI grows a tree of positions from the current one. Each iteration
+    walks down the tree, choosing moves by the UCT formula (the average
+    payoff of a move for the player making it, plus a bonus for moves tried
+    less often), adds one new position to the tree, and plays random moves
+    from there to the end of the game. The payoff for every player is then
+    added up along the path, so each player's choices in the tree are made
+    by their own payoff, however many players there are.
+
+    Chance nodes are walked through by sampling the move of nature. In the
+    end, the AI plays the move that was tried most often.
+
+    The search can also start each iteration from a different position, as
+    long as the player to move at the root has the same legal moves: this is
+    how ismcts.rs searches over guesses of hidden information. Moves are
+    then only chosen among those legal in the position reached.
+
+    The search draws its randomness from the game's random number
+    generator, so with a budget in iterations a game against it can be
+    reproduced from the seed (but not with a budget in time).
+*/
+
+use super::transposition::{HashFn, PositionHash, TranspositionTable};
+use crate::abstract_game::{AbstractGame, Ai, GameStatus};
+use crate::player::Player;
+use crate::random::GameRng;
+
+use rand::seq::SliceRandom;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// How long the AI searches for each move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+/// A position in the search tree
+struct Node<M, const N: usize> {
+    // Moves tried from here, with the positions they lead to
+    children: Vec<(M, usize)>,
+    visits: usize,
+    // Times the move leading here was legal when the parent was walked
+    // through (the same as the parent's visits, unless the legal moves
+    // depend on hidden information)
+    available: usize,
+    // Total payoff for each player over the playouts through here
+    total: [f64; N],
+}
+
+impl<M, const N: usize> Node<M, N> {
+    fn new() -> Self {
+        Self { children: Vec::new(), visits: 0, available: 0, total: [0.0; N] }
+    }
+}
+
+/// AI that searches by random playouts, for games with complete
+/// information
+pub struct MctsAi<G, const N: usize> {
+    budget: Budget,
+    exploration: f64,
+    // Positions in the tree, and how to hash them (if the game can)
+    table: Option<(TranspositionTable<usize>, HashFn<G>)>,
+    game: PhantomData<G>,
+}
+
+/// Play random moves to the end of the game, returning the payoffs
+pub fn random_playout<G, const N: usize>(
+    mut game: G,
+    rng: &mut GameRng,
+) -> [f64; N]
+where
+    G: AbstractGame<N>,
+    G::Move: Clone,
+{
+    loop {
+        match game.status() {
+            GameStatus::ToMove(_) => {
+                let mv = game.legal_moves().choose(rng).cloned();
+                game.make_move(mv.expect("there should be a legal move"));
+            }
+            GameStatus::Simultaneous(plyrs) => {
+                let moves = plyrs
+                    .iter()
+                    .map(|plyr| {
+                        let mv =
+                            game.legal_moves_for(plyr).choose(rng).cloned();
+                        (plyr, mv.expect("there should be a legal move"))
+                    })
+                    .collect();
+                game.make_simultaneous_moves(moves);
+            }
+            GameStatus::Chance => {
+                let mv = game.sample_chance_move(rng);
+                game.make_move(mv);
+            }
+            _ => return game.payoffs().expect("the game should have ended"),
+        }
+    }
+}
+
+impl<G, const N: usize> MctsAi<G, N>
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone,
+{
+    /// Iterations per move for Ai::new
+    pub const DEFAULT_ITERATIONS: usize = 1000;
+    /// Weight of the exploration bonus in the UCT formula, for payoffs
+    /// between 0 and 1
+    pub const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+    pub fn with_options(budget: Budget, exploration: f64) -> Self {
+        Self { budget, exploration, table: None, game: PhantomData }
+    }
+
+    /// Share a node of the tree between all the ways of reaching its
+    /// position, finding them with a transposition table with room for
+    /// capacity positions. Only for games where positions can't repeat.
+    pub fn with_table(mut self, capacity: usize) -> Self
+    where
+        G: PositionHash<N>,
+    {
+        let table = TranspositionTable::new(capacity);
+        self.table = Some((table, <G as PositionHash<N>>::position_hash));
+        self
+    }
+
+    /// The child of a node reached by a move (leading to the position
+    /// next), added to the tree if new
+    fn child(
+        &mut self,
+        tree: &mut Vec<Node<G::Move, N>>,
+        node: usize,
+        mv: G::Move,
+        next: &G,
+    ) -> usize {
+        if let Some(&(_, child)) =
+            tree[node].children.iter().find(|(m, _)| m == &mv)
+        {
+            return child;
+        }
+        let child = match self.table.as_mut() {
+            Some((table, hash)) => {
+                let hash = hash(next);
+                table.get(hash).copied().unwrap_or_else(|| {
+                    tree.push(Node::new());
+                    table.insert(hash, tree.len() - 1);
+                    tree.len() - 1
+                })
+            }
+            None => {
+                tree.push(Node::new());
+                tree.len() - 1
+            }
+        };
+        tree[node].children.push((mv, child));
+        child
+    }
+
+    /// Move chosen by the UCT formula, among the legal moves tried from a
+    /// node, for the player to move there
+    fn select(
+        &self,
+        tree: &[Node<G::Move, N>],
+        node: usize,
+        plyr: Player<N>,
+        legal_moves: &[G::Move],
+    ) -> G::Move {
+        let uct = |child: &Node<G::Move, N>| {
+            let visits = child.visits as f64;
+            let log_available = (child.available as f64).ln();
+            child.total[plyr.as_index()] / visits
+                + self.exploration * (log_available / visits).sqrt()
+        };
+        tree[node]
+            .children
+            .iter()
+            .filter(|(mv, _)| legal_moves.contains(mv))
+            .fold(None, |best: Option<(&G::Move, f64)>, (mv, child)| {
+                let value = uct(&tree[*child]);
+                match best {
+                    Some((_, best_value)) if best_value >= value => best,
+                    _ => Some((mv, value)),
+                }
+            })
+            .expect("there should be a move tried")
+            .0
+            .clone()
+    }
+
+    /// One iteration: walk down the tree, add a position, play out the
+    /// game from there, and add up the payoffs along the way
+    fn iterate(
+        &mut self,
+        tree: &mut Vec<Node<G::Move, N>>,
+        mut game: G,
+        rng: &mut GameRng,
+    ) {
+        let mut node = 0;
+        let mut path = vec![node];
+        loop {
+            let (mv, legal_moves) = match game.status() {
+                GameStatus::ToMove(plyr) => {
+                    let legal_moves = game.legal_moves();
+                    let untried: Vec<&G::Move> = legal_moves
+                        .iter()
+                        .filter(|&mv| {
+                            tree[node].children.iter().all(|(m, _)| m != mv)
+                        })
+                        .collect();
+                    let mv = match untried.choose(rng) {
+                        Some(&mv) => mv.clone(),
+                        None => self.select(tree, node, plyr, &legal_moves),
+                    };
+                    (mv, Some(legal_moves))
+                }
+                GameStatus::Chance => (game.sample_chance_move(rng), None),
+                // Ended, or simultaneous moves (which the tree can't
+                // represent): play out from here
+                _ => break,
+            };
+            let is_new = legal_moves.is_some()
+                && tree[node].children.iter().all(|(m, _)| m != &mv);
+            game.make_move(mv.clone());
+            let child = self.child(tree, node, mv, &game);
+            if let Some(legal_moves) = legal_moves {
+                let available: Vec<usize> = tree[node]
+                    .children
+                    .iter()
+                    .filter(|(m, _)| legal_moves.contains(m))
+                    .map(|&(_, child)| child)
+                    .collect();
+                for child in available {
+                    tree[child].available += 1;
+                }
+            }
+            node = child;
+            path.push(node);
+            if is_new {
+                break;
+            }
+        }
+        let payoffs = random_playout(game, rng);
+        for &node in &path {
+            tree[node].visits += 1;
+            for (total, payoff) in tree[node].total.iter_mut().zip(&payoffs) {
+                *total += payoff;
+            }
+        }
+    }
+
+    /// Search from positions drawn by sample (the same position every
+    /// time, for games with complete information), returning the most
+    /// tried of the legal moves
+    pub(crate) fn search<F>(
+        &mut self,
+        mut sample: F,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move
+    where
+        F: FnMut(&mut GameRng) -> G,
+    {
+        if let Some((table, _)) = self.table.as_mut() {
+            table.clear();
+        }
+        let mut tree = vec![Node::new()];
+        let start = Instant::now();
+        let mut iterations = 0;
+        loop {
+            let root = sample(rng);
+            self.iterate(&mut tree, root, rng);
+            iterations += 1;
+            let done = match self.budget {
+                Budget::Iterations(max) => iterations >= max,
+                Budget::Time(max) => start.elapsed() >= max,
+            };
+            if done {
+                break;
+            }
+        }
+        let visits = |mv: &G::Move| {
+            tree[0]
+                .children
+                .iter()
+                .find(|(m, _)| m == mv)
+                .map_or(0, |&(_, child)| tree[child].visits)
+        };
+        legal_moves
+            .iter()
+            .fold(None, |best: Option<&G::Move>, mv| match best {
+                Some(best) if visits(best) >= visits(mv) => Some(best),
+                _ => Some(mv),
+            })
+            .expect("there should be a legal move")
+            .clone()
+    }
+}
+
+impl<G, const N: usize> Ai<G, N> for MctsAi<G, N>
+where
+    G: AbstractGame<N, Observation = G> + Clone,
+    G::Move: Clone,
+{
+    fn new() -> Self {
+        Self::with_options(
+            Budget::Iterations(Self::DEFAULT_ITERATIONS),
+            Self::DEFAULT_EXPLORATION,
+        )
+    }
+    fn ai_move(
+        &mut self,
+        obs: &G,
+        plyr: Player<N>,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move {
+        if obs.cur_player() != Some(plyr) {
+            // Simultaneous moves: there is no tree to search
+            return legal_moves
+                .choose(rng)
+                .expect("there should be a legal move")
+                .clone();
+        }
+        self.search(|_| obs.clone(), legal_moves, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nim::{NimMove, NimParams, NimState};
+    use crate::pig::PigState;
+    use crate::random;
+
+    fn new_nim<const N: usize>(piles: Vec<usize>) -> NimState<N> {
+        NimState::game_setup(NimParams::new(piles), &mut random::seeded(0))
+    }
+
+    fn best_move<const N: usize>(
+        ai: &mut MctsAi<NimState<N>, N>,
+        game: &NimState<N>,
+        seed: u64,
+    ) -> NimMove {
+        let plyr = game.cur_player().unwrap();
+        let legal_moves = game.legal_moves();
+        ai.ai_move(game, plyr, &legal_moves, &mut random::seeded(seed))
+    }
+
+    #[test]
+    fn test_winning_moves() {
+        let mut ai = MctsAi::new();
+        let game = new_nim::<2>(vec![1, 2]);
+        assert_eq!(best_move(&mut ai, &game, 0), NimMove::new(2, 1));
+        let game = new_nim::<2>(vec![2, 3]);
+        assert_eq!(best_move(&mut ai, &game, 0), NimMove::new(2, 1));
+    }
+
+    #[test]
+    fn test_transposition_table() {
+        let budget =
+            Budget::Iterations(MctsAi::<NimState<2>, 2>::DEFAULT_ITERATIONS);
+        let mut ai = MctsAi::with_options(budget, 1.0).with_table(1000);
+        let game = new_nim::<2>(vec![2, 3]);
+        assert_eq!(best_move(&mut ai, &game, 0), NimMove::new(2, 1));
+        let (table, _) = ai.table.as_ref().unwrap();
+        // 11 sets of piles can be reached, but only 8 up to order (each
+        // with either player to move)
+        assert!(!table.is_empty() && table.len() <= 16);
+    }
+
+    #[test]
+    fn test_three_players() {
+        // Taking the whole of the last pile wins at once
+        let mut ai = MctsAi::new();
+        let game = new_nim::<3>(vec![4]);
+        assert_eq!(best_move(&mut ai, &game, 0), NimMove::new(1, 4));
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let mut ai = MctsAi::with_options(Budget::Iterations(50), 1.0);
+        let game = new_nim::<2>(vec![4, 5, 6, 7]);
+        for seed in 0..5 {
+            assert_eq!(
+                best_move(&mut ai, &game, seed),
+                best_move(&mut ai, &game, seed)
+            );
+        }
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let budget = Budget::Time(Duration::from_millis(20));
+        let mut ai = MctsAi::with_options(budget, 1.0);
+        let game = new_nim::<2>(vec![10, 20, 30]);
+        let start = Instant::now();
+        let mv = best_move(&mut ai, &game, 0);
+        assert!(game.is_valid_move(&mv));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_chance_nodes() {
+        let mut rng = random::seeded(0);
+        let mut game = PigState::<2>::game_setup(20, &mut rng);
+        let mut ai =
+            MctsAi::<PigState<2>, 2>::with_options(Budget::Iterations(50), 1.0);
+        while !game.is_ended() {
+            let mv = match game.cur_player() {
+                Some(plyr) => {
+                    let legal_moves = game.legal_moves();
+                    ai.ai_move(&game, plyr, &legal_moves, &mut rng)
+                }
+                None => game.sample_chance_move(&mut rng),
+            };
+            assert!(game.is_valid_move(&mv));
+            game.make_move(mv);
+        }
+    }
+}
@@ -0,0 +1,471 @@
+/*
+    Minimax search for games with complete information
+
+    The AI searches the game tree to a fixed depth, scoring positions where
+    the game has ended by their payoffs and positions where the search stops
+    by a static evaluation (the Evaluate trait). Chance nodes are scored by
+    the expected value of their outcomes.
+
+    With more than two players there is no single value to maximize, so
+    there are two ways to search:
+    - paranoid: assume all the other players are out to minimize our
+      payoff, which turns the game into a two-player game, so alpha-beta
+      pruning applies
+    - max-n: assume every player maximizes their own payoff, backing up a
+      payoff for each player (no pruning)
+    With two players whose payoffs add up to a constant (as with the default
+    payoffs), both give ordinary minimax.
+*/
+
+use super::transposition::{HashFn, PositionHash, TranspositionTable};
+use crate::abstract_game::{AbstractGame, Ai, GameStatus};
+use crate::player::Player;
+use crate::random::GameRng;
+
+use rand::seq::SliceRandom;
+use std::marker::PhantomData;
+
+/// Static evaluation of positions, for when the search stops before the
+/// game has ended
+pub trait Evaluate<G, const N: usize> {
+    /// Estimated payoff for each player of a position that hasn't ended,
+    /// on the same scale as AbstractGame::payoffs
+    fn evaluate(&self, game: &G) -> [f64; N];
+}
+
+/// Evaluation that knows nothing about the game: every player is given an
+/// equal share, as in a draw
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Uninformed;
+
+impl<G, const N: usize> Evaluate<G, N> for Uninformed {
+    fn evaluate(&self, _game: &G) -> [f64; N] {
+        [1.0 / N as f64; N]
+    }
+}
+
+/// How the other players are assumed to play (see the top of this file)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Search {
+    Paranoid,
+    MaxN,
+}
+
+/// Bound on a value found by alpha-beta search (outside the search
+/// window, only a bound is known)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// What a search found out about a position, for the transposition table
+#[derive(Debug, Clone, Copy)]
+enum Entry<const N: usize> {
+    MaxN { depth: usize, value: [f64; N] },
+    Paranoid { depth: usize, me: Player<N>, value: f64, bound: Bound },
+}
+
+/// AI that searches a fixed number of moves ahead
+/// E is the evaluation of positions where the search stops.
+pub struct MinimaxAi<G, E, const N: usize> {
+    depth: usize,
+    search: Search,
+    eval: E,
+    // Positions searched before, and how to hash them (if the game can)
+    table: Option<(TranspositionTable<Entry<N>>, HashFn<G>)>,
+    game: PhantomData<G>,
+}
+
+impl<G, E, const N: usize> MinimaxAi<G, E, N>
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone,
+    E: Evaluate<G, N>,
+{
+    /// Depth searched by Ai::new, counting moves by chance
+    pub const DEFAULT_DEPTH: usize = 4;
+
+    pub fn with_options(depth: usize, search: Search, eval: E) -> Self {
+        Self { depth, search, eval, table: None, game: PhantomData }
+    }
+
+    /// Remember the values of positions in a transposition table, with
+    /// room for capacity positions. The table is kept from move to move.
+    pub fn with_table(mut self, capacity: usize) -> Self
+    where
+        G: PositionHash<N>,
+    {
+        let table = TranspositionTable::new(capacity);
+        self.table = Some((table, <G as PositionHash<N>>::position_hash));
+        self
+    }
+
+    /// Hash of a position, if there is a transposition table
+    fn position_hash(&self, game: &G) -> Option<u64> {
+        self.table.as_ref().map(|(_, hash)| hash(game))
+    }
+
+    fn lookup(&self, hash: Option<u64>) -> Option<Entry<N>> {
+        let (table, _) = self.table.as_ref()?;
+        table.get(hash?).copied()
+    }
+
+    fn store(&mut self, hash: Option<u64>, entry: Entry<N>) {
+        if let (Some((table, _)), Some(hash)) = (self.table.as_mut(), hash) {
+            table.insert(hash, entry);
+        }
+    }
+
+    /// Value of the position to a game that stops here, if the search
+    /// should go no further
+    fn leaf_value(&self, game: &G, depth: usize) -> Option<[f64; N]> {
+        if let Some(payoffs) = game.payoffs() {
+            Some(payoffs)
+        } else if depth == 0
+            || matches!(game.status(), GameStatus::Simultaneous(_))
+        {
+            // Simultaneous moves can't be searched move by move
+            Some(self.eval.evaluate(game))
+        } else {
+            None
+        }
+    }
+
+    /// Position after a move
+    fn after(game: &G, mv: G::Move) -> G {
+        let mut next = game.clone();
+        next.make_move(mv);
+        next
+    }
+
+    /// Payoff for each player, assuming each maximizes their own
+    fn max_n(&mut self, game: &G, depth: usize) -> [f64; N] {
+        if let Some(value) = self.leaf_value(game, depth) {
+            return value;
+        }
+        let hash = self.position_hash(game);
+        if let Some(Entry::MaxN { depth: searched, value }) = self.lookup(hash)
+        {
+            if searched >= depth {
+                return value;
+            }
+        }
+        let value = match game.status() {
+            GameStatus::ToMove(plyr) => {
+                let mut best: Option<[f64; N]> = None;
+                for mv in game.legal_moves() {
+                    let value = self.max_n(&Self::after(game, mv), depth - 1);
+                    match best {
+                        Some(best)
+                            if best[plyr.as_index()]
+                                >= value[plyr.as_index()] => {}
+                        _ => best = Some(value),
+                    }
+                }
+                best.expect("there should be a legal move")
+            }
+            GameStatus::Chance => {
+                let mut result = [0.0; N];
+                for (mv, prob) in game.chance_outcomes() {
+                    let value = self.max_n(&Self::after(game, mv), depth - 1);
+                    for (total, v) in result.iter_mut().zip(value.iter()) {
+                        *total += prob * v;
+                    }
+                }
+                result
+            }
+            _ => unreachable!("leaf positions are handled above"),
+        };
+        self.store(hash, Entry::MaxN { depth, value });
+        value
+    }
+
+    /// Payoff for me, assuming everyone else is out to minimize it; only
+    /// exact when it lies strictly between alpha and beta
+    fn paranoid(
+        &mut self,
+        game: &G,
+        me: Player<N>,
+        depth: usize,
+        mut alpha: f64,
+        mut beta: f64,
+    ) -> f64 {
+        if let Some(value) = self.leaf_value(game, depth) {
+            return value[me.as_index()];
+        }
+        let hash = self.position_hash(game);
+        let original_alpha = alpha;
+        if let Some(Entry::Paranoid { depth: searched, me: m, value, bound }) =
+            self.lookup(hash)
+        {
+            if searched >= depth && m == me {
+                match bound {
+                    Bound::Exact => return value,
+                    Bound::Lower => alpha = alpha.max(value),
+                    Bound::Upper => beta = beta.min(value),
+                }
+                if alpha >= beta {
+                    return value;
+                }
+            }
+        }
+        let (value, bound) = match game.status() {
+            GameStatus::ToMove(plyr) => {
+                let maximizing = plyr == me;
+                let mut best =
+                    if maximizing { f64::NEG_INFINITY } else { f64::INFINITY };
+                for mv in game.legal_moves() {
+                    let next = Self::after(game, mv);
+                    let value =
+                        self.paranoid(&next, me, depth - 1, alpha, beta);
+                    if maximizing {
+                        best = best.max(value);
+                        alpha = alpha.max(value);
+                    } else {
+                        best = best.min(value);
+                        beta = beta.min(value);
+                    }
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+                let bound = if best <= original_alpha {
+                    Bound::Upper
+                } else if best >= beta {
+                    Bound::Lower
+                } else {
+                    Bound::Exact
+                };
+                (best, bound)
+            }
+            GameStatus::Chance => {
+                let mut result = 0.0;
+                for (mv, prob) in game.chance_outcomes() {
+                    let next = Self::after(game, mv);
+                    let value = self.paranoid(
+                        &next,
+                        me,
+                        depth - 1,
+                        f64::NEG_INFINITY,
+                        f64::INFINITY,
+                    );
+                    result += prob * value;
+                }
+                (result, Bound::Exact)
+            }
+            _ => unreachable!("leaf positions are handled above"),
+        };
+        self.store(hash, Entry::Paranoid { depth, me, value, bound });
+        value
+    }
+
+    /// Value of each move for plyr (the first of the best moves is exact)
+    fn move_values(
+        &mut self,
+        game: &G,
+        plyr: Player<N>,
+        legal_moves: &[G::Move],
+    ) -> Vec<f64> {
+        let depth = self.depth.max(1) - 1;
+        let mut alpha = f64::NEG_INFINITY;
+        let mut result = Vec::new();
+        for mv in legal_moves {
+            let next = Self::after(game, mv.clone());
+            let value = match self.search {
+                Search::Paranoid => {
+                    let value =
+                        self.paranoid(&next, plyr, depth, alpha, f64::INFINITY);
+                    alpha = alpha.max(value);
+                    value
+                }
+                Search::MaxN => self.max_n(&next, depth)[plyr.as_index()],
+            };
+            result.push(value);
+        }
+        result
+    }
+}
+
+impl<G, E, const N: usize> Ai<G, N> for MinimaxAi<G, E, N>
+where
+    G: AbstractGame<N, Observation = G> + Clone,
+    G::Move: Clone,
+    E: Evaluate<G, N> + Default,
+{
+    fn new() -> Self {
+        Self::with_options(Self::DEFAULT_DEPTH, Search::Paranoid, E::default())
+    }
+    fn ai_move(
+        &mut self,
+        obs: &G,
+        plyr: Player<N>,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move {
+        if obs.cur_player() != Some(plyr) {
+            // Simultaneous moves: there is nothing to search
+            return legal_moves
+                .choose(rng)
+                .expect("there should be a legal move")
+                .clone();
+        }
+        let values = self.move_values(obs, plyr, legal_moves);
+        let best = (0..legal_moves.len())
+            .fold(None, |best: Option<usize>, i| match best {
+                Some(best) if values[best] >= values[i] => Some(best),
+                _ => Some(i),
+            })
+            .expect("there should be a legal move");
+        legal_moves[best].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impartial::ImpartialGame;
+    use crate::nim::ai::nim_sum;
+    use crate::nim::{NimMove, NimParams, NimState};
+    use crate::pig::PigState;
+    use crate::random;
+
+    fn new_nim<const N: usize>(piles: Vec<usize>) -> NimState<N> {
+        NimState::game_setup(NimParams::new(piles), &mut random::seeded(0))
+    }
+
+    /// Evaluation of two-player Nim from the nim-sum
+    #[derive(Default)]
+    struct NimSumEval;
+
+    impl Evaluate<NimState<2>, 2> for NimSumEval {
+        fn evaluate(&self, game: &NimState<2>) -> [f64; 2] {
+            let mover = game.cur_player().unwrap().as_index();
+            let mut result = [0.0; 2];
+            let winner = if nim_sum(&game.components()) == 0 {
+                1 - mover
+            } else {
+                mover
+            };
+            result[winner] = 1.0;
+            result
+        }
+    }
+
+    /// Play two-player Nim with the AI as player 1 against an opponent who
+    /// takes as much as possible, returning the winner
+    fn play_nim<A: Ai<NimState<2>, 2>>(ai: &mut A, piles: Vec<usize>) -> usize {
+        let mut rng = random::seeded(0);
+        let mut game: NimState<2> = new_nim(piles);
+        while let Some(plyr) = game.cur_player() {
+            let legal_moves = game.legal_moves();
+            let mv = if plyr.as_index() == 0 {
+                ai.ai_move(&game, plyr, &legal_moves, &mut rng)
+            } else {
+                legal_moves.last().unwrap().clone()
+            };
+            assert!(game.is_valid_move(&mv));
+            game.make_move(mv);
+        }
+        match game.status() {
+            GameStatus::Won(plyr) => plyr.as_index(),
+            status => panic!("unexpected status {:?}", status),
+        }
+    }
+
+    #[test]
+    fn test_solves_nim() {
+        // With 7 sticks, searching 7 moves reaches the end of the game
+        for &search in &[Search::Paranoid, Search::MaxN] {
+            let mut ai = MinimaxAi::with_options(7, search, Uninformed);
+            assert_eq!(play_nim(&mut ai, vec![1, 2, 4]), 0, "{:?}", search);
+        }
+    }
+
+    #[test]
+    fn test_transposition_table() {
+        for &search in &[Search::Paranoid, Search::MaxN] {
+            let mut ai =
+                MinimaxAi::with_options(9, search, Uninformed).with_table(1000);
+            assert_eq!(play_nim(&mut ai, vec![2, 3, 4]), 0, "{:?}", search);
+            let (table, _) = ai.table.as_ref().unwrap();
+            assert!(!table.is_empty());
+        }
+        // Same choices with and without the table
+        let game: NimState<2> = new_nim(vec![1, 3, 4]);
+        let plyr = game.cur_player().unwrap();
+        let legal_moves = game.legal_moves();
+        type Ai2 = MinimaxAi<NimState<2>, Uninformed, 2>;
+        let mut with_table =
+            Ai2::with_options(3, Search::Paranoid, Uninformed).with_table(50);
+        let mut without = Ai2::with_options(3, Search::Paranoid, Uninformed);
+        for seed in 0..3 {
+            let mut rng = random::seeded(seed);
+            assert_eq!(
+                with_table.ai_move(&game, plyr, &legal_moves, &mut rng),
+                without.ai_move(&game, plyr, &legal_moves, &mut rng)
+            );
+        }
+    }
+
+    #[test]
+    fn test_nim_out_of_the_box() {
+        let game: NimState<2> = new_nim(vec![1, 1, 2]);
+        let mut ai = MinimaxAi::<NimState<2>, Uninformed, 2>::new();
+        let plyr = game.cur_player().unwrap();
+        let mv = ai.ai_move(
+            &game,
+            plyr,
+            &game.legal_moves(),
+            &mut random::seeded(0),
+        );
+        assert_eq!(mv, NimMove::new(3, 2));
+    }
+
+    #[test]
+    fn test_evaluation() {
+        // Too many sticks to search to the end, but the evaluation knows
+        // who is winning
+        let mut ai = MinimaxAi::with_options(1, Search::Paranoid, NimSumEval);
+        assert_eq!(play_nim(&mut ai, vec![9, 12, 14, 17]), 0);
+    }
+
+    #[test]
+    fn test_three_players() {
+        // Taking the whole of the last pile wins at once
+        let game: NimState<3> = new_nim(vec![3]);
+        let plyr = game.cur_player().unwrap();
+        for &search in &[Search::Paranoid, Search::MaxN] {
+            let mut ai = MinimaxAi::<NimState<3>, _, 3>::with_options(
+                3, search, Uninformed,
+            );
+            let mv = ai.ai_move(
+                &game,
+                plyr,
+                &game.legal_moves(),
+                &mut random::seeded(0),
+            );
+            assert_eq!(mv, NimMove::new(1, 3), "{:?}", search);
+        }
+    }
+
+    #[test]
+    fn test_chance_nodes() {
+        // Pig, with rolls of the die searched by expected value
+        let mut rng = random::seeded(0);
+        let mut game = PigState::<2>::game_setup(20, &mut rng);
+        let mut ai = MinimaxAi::<PigState<2>, Uninformed, 2>::new();
+        while !game.is_ended() {
+            let mv = match game.cur_player() {
+                Some(plyr) => {
+                    let legal_moves = game.legal_moves();
+                    ai.ai_move(&game, plyr, &legal_moves, &mut rng)
+                }
+                None => game.sample_chance_move(&mut rng),
+            };
+            assert!(game.is_valid_move(&mv));
+            game.make_move(mv);
+        }
+    }
+}
@@ -0,0 +1,15 @@
+/*
+    General-purpose AIs, which can play any game (or any game of a broad
+    kind). AIs for particular games live with those games.
+*/
+
+pub mod ismcts;
+pub mod mcts;
+pub mod minimax;
+pub mod random_ai;
+pub mod transposition;
+
+pub use ismcts::IsmctsAi;
+pub use mcts::MctsAi;
+pub use minimax::MinimaxAi;
+pub use random_ai::RandomAi;
@@ -0,0 +1,112 @@
+/*
+    AI that plays uniformly random legal moves
+
+    This works for any game, so it is the baseline opponent for games
+    without a better AI. Its choices are drawn from the game's random number
+    generator, so a game against it can be reproduced from the seed.
+*/
+
+use crate::abstract_game::{AbstractGame, Ai};
+use crate::player::Player;
+use crate::random::GameRng;
+
+use rand::seq::SliceRandom;
+use std::marker::PhantomData;
+
+pub struct RandomAi<G, const N: usize> {
+    game: PhantomData<G>,
+}
+
+impl<G, const N: usize> Ai<G, N> for RandomAi<G, N>
+where
+    G: AbstractGame<N>,
+    G::Move: Clone,
+{
+    fn new() -> Self {
+        Self { game: PhantomData }
+    }
+    fn ai_move(
+        &mut self,
+        _obs: &G::Observation,
+        _plyr: Player<N>,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move {
+        legal_moves.choose(rng).expect("there should be a legal move").clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_game::{GameStatus, Turn};
+    use crate::battleship::game::{BattleshipParams, GameState};
+    use crate::kayles::KaylesState;
+    use crate::nim::{NimParams, NimState};
+    use crate::pig::PigState;
+    use crate::random;
+    use crate::rps::RpsState;
+
+    /// Play a game to the end with RandomAi for every player, returning
+    /// the moves made
+    fn play_out<G, const N: usize>(
+        params: G::SetupParams,
+        seed: u64,
+    ) -> Vec<Turn<G::Move, N>>
+    where
+        G: AbstractGame<N>,
+        G::Move: Clone,
+    {
+        let mut rng = random::seeded(seed);
+        let mut game = G::game_setup(params, &mut rng);
+        let mut ai = RandomAi::<G, N>::new();
+        let mut turns = Vec::new();
+        loop {
+            let turn = match game.status() {
+                GameStatus::ToMove(plyr) => {
+                    let legal_moves = game.legal_moves();
+                    let obs = game.observe(plyr);
+                    let mv = ai.ai_move(&obs, plyr, &legal_moves, &mut rng);
+                    assert!(game.is_valid_move(&mv));
+                    Turn::Move(mv)
+                }
+                GameStatus::Simultaneous(plyrs) => Turn::Simultaneous(
+                    plyrs
+                        .iter()
+                        .map(|plyr| {
+                            let legal_moves = game.legal_moves_for(plyr);
+                            let obs = game.observe(plyr);
+                            (
+                                plyr,
+                                ai.ai_move(&obs, plyr, &legal_moves, &mut rng),
+                            )
+                        })
+                        .collect(),
+                ),
+                GameStatus::Chance => {
+                    Turn::Move(game.sample_chance_move(&mut rng))
+                }
+                _ => return turns,
+            };
+            turns.push(turn.clone());
+            game.make_turn(turn);
+        }
+    }
+
+    #[test]
+    fn test_plays_every_game() {
+        play_out::<NimState<3>, 3>(NimParams::new(vec![3, 4, 5]), 0);
+        play_out::<KaylesState, 2>(vec![10], 0);
+        play_out::<PigState<2>, 2>(20, 0);
+        play_out::<RpsState, 2>(3, 0);
+        play_out::<GameState, 2>(BattleshipParams::default(), 0);
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let params = NimParams::new(vec![5, 6, 7]);
+        let turns1 = play_out::<NimState<2>, 2>(params.clone(), 7);
+        let turns2 = play_out::<NimState<2>, 2>(params, 7);
+        assert!(turns1 == turns2);
+    }
+}
@@ -0,0 +1,151 @@
+/*
+    Transposition tables
+
+    A search often reaches the same position by different orders of moves
+    (taking from pile 1 then pile 2 in Nim, or the same shots in a different
+    order in Battleship), or reaches positions that are the same up to
+    symmetry (Nim piles in a different order). Games that can hash their
+    positions (the PositionHash trait) let search AIs remember what they
+    found out about each position in a transposition table.
+
+    There are two usual ways to hash a position:
+    - canonical hashing: put the position in a standard form (e.g. sort the
+      piles) and hash that, with hash_value
+    - Zobrist hashing: XOR together a random key (zobrist_key) for each
+      feature of the position (e.g. each shot on a board). Since XOR is
+      its own inverse, this can be updated as moves are made, and the order
+      of the moves doesn't matter.
+
+    The table has a fixed number of slots, so memory use is bounded: when
+    two positions want the same slot, the newer one replaces the older.
+*/
+
+use crate::abstract_game::AbstractGame;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A game whose positions can be hashed
+pub trait PositionHash<const N: usize>: AbstractGame<N> {
+    /// Hash of the position. Positions of the same game that are the same
+    /// (or the same up to a symmetry, so just as good for each player) must
+    /// have the same hash; other positions should have different hashes,
+    /// with high probability.
+    fn position_hash(&self) -> u64;
+}
+
+/// How a search AI hashes positions: PositionHash::position_hash, for a
+/// game that has it
+pub type HashFn<G> = fn(&G) -> u64;
+
+/// Hash of a value (e.g. a position in canonical form)
+/// This is the same every time the program is run.
+pub fn hash_value<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Random-looking key for a feature of a position, for Zobrist hashing
+/// (the SplitMix64 mixing function, so no table of keys is needed)
+pub fn zobrist_key(feature: u64) -> u64 {
+    let mut z = feature.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Table from position hashes to what is known about the positions, with
+/// a fixed number of slots
+#[derive(Debug, Clone)]
+pub struct TranspositionTable<V> {
+    slots: Vec<Option<(u64, V)>>,
+    len: usize,
+}
+
+impl<V> TranspositionTable<V> {
+    /// Table with room for (at most) capacity positions
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { slots: (0..capacity).map(|_| None).collect(), len: 0 }
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        (hash % self.slots.len() as u64) as usize
+    }
+
+    pub fn get(&self, hash: u64) -> Option<&V> {
+        match &self.slots[self.slot(hash)] {
+            Some((h, value)) if *h == hash => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Store what is known about a position, replacing whatever was in its
+    /// slot
+    pub fn insert(&mut self, hash: u64, value: V) {
+        let slot = self.slot(hash);
+        if self.slots[slot].is_none() {
+            self.len += 1;
+        }
+        self.slots[slot] = Some((hash, value));
+    }
+
+    /// Number of positions stored
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn clear(&mut self) {
+        for slot in self.slots.iter_mut() {
+            *slot = None;
+        }
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let mut table = TranspositionTable::new(4);
+        table.insert(1, "a");
+        table.insert(2, "b");
+        assert_eq!(table.get(1), Some(&"a"));
+        assert_eq!(table.get(3), None);
+        assert_eq!(table.len(), 2);
+        // 5 goes in the same slot as 1
+        table.insert(5, "c");
+        assert_eq!(table.get(1), None);
+        assert_eq!(table.get(5), Some(&"c"));
+        assert_eq!(table.len(), 2);
+        table.clear();
+        assert!(table.is_empty());
+        assert_eq!(table.capacity(), 4);
+    }
+
+    #[test]
+    fn test_zobrist_order() {
+        let features = [3, 14, 15, 92];
+        let forward = features.iter().fold(0, |h, &f| h ^ zobrist_key(f));
+        let backward =
+            features.iter().rev().fold(0, |h, &f| h ^ zobrist_key(f));
+        assert_eq!(forward, backward);
+        assert_ne!(zobrist_key(0), zobrist_key(1));
+        // Undoing a feature undoes its key
+        assert_eq!(
+            forward ^ zobrist_key(92),
+            features[..3].iter().fold(0, |h, &f| h ^ zobrist_key(f))
+        );
+    }
+}
@@ -0,0 +1,384 @@
+/*
+    AIs for Battleship
+
+    Hunt and target
+
+    The fleet is placed at random (but legally). When shooting, the AI
+    looks only at the public view of the opponent's board:
+    - after a hit on a ship that hasn't sunk yet, it targets the squares
+      next to it, preferring to extend a line of hits (a ship is a
+      straight line)
+    - otherwise it hunts on a checkerboard pattern, since every ship that
+      lies along a row or column covers a square of each colour (diagonal
+      ships may not, so once the checkerboard is used up it tries the rest)
+    Under the rule variants, it only looks along the directions ships may
+    lie in, and skips squares next to sunk ships when ships can't touch.
+
+    Probability density
+    For every square, the AI counts the ways the opponent's ships that are
+    still afloat could be placed over it under the rules, consistent with
+    the hits, misses and sunk ships so far, and fires at the most likely
+    square. Placements through hits count for much more, so that it
+    finishes off ships it has found. It places its own ships where the
+    opponent's counts (with nothing known) are lowest.
+*/
+
+use super::board::{Coord, PublicBoard, Square};
+use super::game::{self, GameState, Move, Observation, ShipType};
+use super::rules::Rules;
+
+use crate::abstract_game::Ai;
+use crate::player::TwoPlayers;
+use crate::random::GameRng;
+
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+pub struct HuntTargetAi;
+
+/// Whether a square hasn't been shot at yet
+fn is_unshot(board: &PublicBoard, coord: Coord) -> bool {
+    board.get(coord) == Square::Sea
+}
+
+/// Whether a square hasn't been shot at yet and could hold a ship (it
+/// can't be next to a sunk ship, if ships can't touch)
+fn is_open(board: &PublicBoard, rules: &Rules, coord: Coord) -> bool {
+    is_unshot(board, coord)
+        && !(rules.no_touching
+            && board
+                .size()
+                .neighbors(&[coord])
+                .iter()
+                .any(|&c| board.get(c) == Square::ShipSunk))
+}
+
+/// Open squares at either end of a line of two or more hits
+fn line_ends(board: &PublicBoard, rules: &Rules) -> Vec<Coord> {
+    let mut result = Vec::new();
+    for coord in
+        board.size().coords().filter(|&c| board.get(c) == Square::ShipHit)
+    {
+        for dir in rules.dirs() {
+            let next = board.size().step(coord, dir);
+            if !matches!(next, Some(next) if board.get(next) == Square::ShipHit)
+            {
+                continue;
+            }
+            // Walk back to the far end of the line of hits
+            let mut end = coord;
+            while let Some(prev) = board.size().step(end, dir.opposite()) {
+                if board.get(prev) != Square::ShipHit {
+                    if is_open(board, rules, prev) && !result.contains(&prev) {
+                        result.push(prev);
+                    }
+                    break;
+                }
+                end = prev;
+            }
+        }
+    }
+    result
+}
+
+/// Open squares next to a hit, along the directions ships may lie in:
+/// rows and columns (orthogonal) or diagonals
+fn hit_neighbors(
+    board: &PublicBoard,
+    rules: &Rules,
+    orthogonal: bool,
+) -> Vec<Coord> {
+    let mut result = Vec::new();
+    for coord in
+        board.size().coords().filter(|&c| board.get(c) == Square::ShipHit)
+    {
+        for dir in rules.dirs().filter(|dir| dir.is_orthogonal() == orthogonal)
+        {
+            if let Some(next) = board.size().step(coord, dir) {
+                if is_open(board, rules, next) && !result.contains(&next) {
+                    result.push(next);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Choose a square to shoot at, from the public view of the opponent's
+/// board
+pub fn choose_shot(
+    board: &PublicBoard,
+    rules: &Rules,
+    rng: &mut GameRng,
+) -> Coord {
+    let open: Vec<Coord> =
+        board.size().coords().filter(|&c| is_open(board, rules, c)).collect();
+    let hunt: Vec<Coord> =
+        open.iter().copied().filter(|c| (c.row() + c.col()) % 2 == 0).collect();
+    let any: Vec<Coord> =
+        board.size().coords().filter(|&c| is_unshot(board, c)).collect();
+    let candidates = [
+        line_ends(board, rules),
+        hit_neighbors(board, rules, true),
+        hit_neighbors(board, rules, false),
+        hunt,
+        open,
+        any,
+    ];
+    let targets = candidates
+        .iter()
+        .find(|coords| !coords.is_empty())
+        .expect("there should be a square left to shoot at");
+    *targets.choose(rng).unwrap()
+}
+
+impl Ai<GameState, 2> for HuntTargetAi {
+    fn new() -> Self {
+        Self
+    }
+    fn ai_move(
+        &mut self,
+        obs: &Observation,
+        _plyr: TwoPlayers,
+        legal_moves: &[Move],
+        rng: &mut GameRng,
+    ) -> Move {
+        if obs.pending().is_empty() {
+            Move::Shoot(choose_shot(obs.opponent_board(), obs.rules(), rng))
+        } else {
+            // Every legal move places a ship
+            *legal_moves.choose(rng).expect("there should be a legal move")
+        }
+    }
+}
+
+/// How much more a placement counts for each hit it goes through
+const HIT_WEIGHT: f64 = 20.0;
+
+pub struct DensityAi;
+
+/// For each unshot square, the (weighted) number of placements of the
+/// given ships that cover it and are consistent with the board and rules
+pub fn density(
+    board: &PublicBoard,
+    ships: &[ShipType],
+    rules: &Rules,
+) -> HashMap<Coord, f64> {
+    let mut result: HashMap<Coord, f64> = HashMap::new();
+    for ship in ships {
+        for coord in board.size().coords() {
+            for dir in rules.dirs() {
+                let squares = match board.size().line(coord, dir, ship.length())
+                {
+                    Some(squares) => squares,
+                    None => continue,
+                };
+                let shots = squares.iter().map(|&c| board.get(c));
+                let blocked =
+                    |sq| sq == Square::SeaMiss || sq == Square::ShipSunk;
+                if shots.clone().any(blocked)
+                    || !rules.allows_hidden_placement(board, dir, &squares)
+                {
+                    continue;
+                }
+                let hits = shots.filter(|&sq| sq == Square::ShipHit).count();
+                let weight = HIT_WEIGHT.powi(hits as i32);
+                for &square in squares.iter().filter(|&&c| is_unshot(board, c))
+                {
+                    *result.entry(square).or_insert(0.0) += weight;
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Choose the unshot square most likely to hold a ship (at random among
+/// the most likely)
+pub fn choose_densest_shot(
+    board: &PublicBoard,
+    ships: &[ShipType],
+    rules: &Rules,
+    rng: &mut GameRng,
+) -> Coord {
+    let density = density(board, ships, rules);
+    let max = density.values().cloned().fold(0.0, f64::max);
+    let best: Vec<Coord> = board
+        .size()
+        .coords()
+        .filter(|c| is_unshot(board, *c))
+        .filter(|c| density.get(c).map_or(0.0, |&d| d) >= max)
+        .collect();
+    *best.choose(rng).expect("there should be a square left to shoot at")
+}
+
+impl Ai<GameState, 2> for DensityAi {
+    fn new() -> Self {
+        Self
+    }
+    fn ai_move(
+        &mut self,
+        obs: &Observation,
+        _plyr: TwoPlayers,
+        legal_moves: &[Move],
+        rng: &mut GameRng,
+    ) -> Move {
+        if obs.pending().is_empty() {
+            let board = obs.opponent_board();
+            let ships = game::ships_afloat(obs.fleet(), board);
+            let shot = choose_densest_shot(board, &ships, obs.rules(), rng);
+            return Move::Shoot(shot);
+        }
+        // Place a ship where the opponent is least likely to look: at
+        // random among the quarter of placements covering the least
+        // density (on a board with no shots yet)
+        let own_view = obs.own_board().public_view();
+        let prior = density(&own_view, obs.fleet(), obs.rules());
+        let size = obs.own_board().size();
+        let score = |mv: &Move| match *mv {
+            Move::PlaceShip(ship, coord, dir) => size
+                .line(coord, dir, ship.length())
+                .expect("placement should fit on the board")
+                .iter()
+                .map(|c| prior[c])
+                .sum::<f64>(),
+            Move::Shoot(_) => unreachable!("no shots during placement"),
+        };
+        let mut scored: Vec<(f64, Move)> =
+            legal_moves.iter().map(|mv| (score(mv), *mv)).collect();
+        scored.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+        let best = &scored[..scored.len().div_ceil(4)];
+        best.choose(rng).expect("there should be a legal move").1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battleship::board::{Board, Dir};
+    use crate::battleship::game::BattleshipParams;
+    use crate::random;
+
+    fn coord(row: usize, col: usize) -> Coord {
+        Coord::from_usize(row, col).unwrap()
+    }
+
+    fn board_with_ship() -> Board {
+        let mut board = Board::default();
+        let right = Dir::from_isize(0, 1).unwrap();
+        let ship = ShipType::from_usize(4);
+        assert!(board.place_ship(ship, coord(2, 2), right));
+        board
+    }
+
+    #[test]
+    fn test_hunt_parity() {
+        let board = board_with_ship().public_view();
+        let mut rng = random::seeded(0);
+        for _ in 0..20 {
+            let shot = choose_shot(&board, &Rules::default(), &mut rng);
+            assert_eq!((shot.row() + shot.col()) % 2, 0);
+        }
+    }
+
+    #[test]
+    fn test_target_after_hit() {
+        let mut board = board_with_ship();
+        board.shoot(coord(2, 3));
+        board.shoot(coord(1, 3));
+        let view = board.public_view();
+        let mut rng = random::seeded(0);
+        let expected = [coord(3, 3), coord(2, 2), coord(2, 4)];
+        for _ in 0..20 {
+            assert!(expected.contains(&choose_shot(
+                &view,
+                &Rules::default(),
+                &mut rng
+            )));
+        }
+    }
+
+    #[test]
+    fn test_extend_line() {
+        let mut board = board_with_ship();
+        board.shoot(coord(2, 3));
+        board.shoot(coord(2, 4));
+        board.shoot(coord(2, 2));
+        let view = board.public_view();
+        let mut rng = random::seeded(0);
+        let expected = [coord(2, 1), coord(2, 5)];
+        for _ in 0..20 {
+            assert!(expected.contains(&choose_shot(
+                &view,
+                &Rules::default(),
+                &mut rng
+            )));
+        }
+    }
+
+    #[test]
+    fn test_no_touching() {
+        let mut board = board_with_ship();
+        for col in 2..6 {
+            board.shoot(coord(2, col));
+        }
+        let view = board.public_view();
+        let rules: Rules = "orthogonal no-touching".parse().unwrap();
+        let sunk = view.sunk_ships()[0].squares();
+        let next_to_sunk = view.size().neighbors(&sunk);
+        let ships = [ShipType::from_usize(3)];
+        let density = density(&view, &ships, &rules);
+        assert!(next_to_sunk.iter().all(|c| !density.contains_key(c)));
+        let mut rng = random::seeded(0);
+        for _ in 0..20 {
+            let shot = choose_shot(&view, &rules, &mut rng);
+            assert!(!next_to_sunk.contains(&shot));
+        }
+    }
+
+    #[test]
+    fn test_density() {
+        let board = Board::default().public_view();
+        let ships = [ShipType::from_usize(3)];
+        let density = density(&board, &ships, &Rules::default());
+        // Corners are least likely, the middle most likely
+        assert!(density[&coord(0, 0)] < density[&coord(0, 1)]);
+        assert!(density[&coord(0, 0)] < density[&coord(5, 5)]);
+        let mut rng = random::seeded(0);
+        let shot =
+            choose_densest_shot(&board, &ships, &Rules::default(), &mut rng);
+        assert!(shot.row() >= 2 && shot.row() <= 7);
+        assert!(shot.col() >= 2 && shot.col() <= 7);
+    }
+
+    #[test]
+    fn test_density_targets_hits() {
+        let mut board = board_with_ship();
+        board.shoot(coord(2, 3));
+        board.shoot(coord(2, 4));
+        let view = board.public_view();
+        let mut rng = random::seeded(0);
+        let shot = choose_densest_shot(
+            &view,
+            &BattleshipParams::default().fleet,
+            &Rules::default(),
+            &mut rng,
+        );
+        assert!([coord(2, 2), coord(2, 5)].contains(&shot));
+    }
+
+    #[test]
+    fn test_density_avoids_misses() {
+        let mut board = Board::default();
+        for coord in board.size().coords().filter(|c| c.row() != 4) {
+            board.shoot(coord);
+        }
+        let view = board.public_view();
+        let ships = [ShipType::from_usize(3)];
+        // Only horizontal placements in row 4 are left
+        let density = density(&view, &ships, &Rules::default());
+        assert_eq!(density.len(), 10);
+        assert_eq!(density[&coord(4, 0)], 2.0);
+        assert_eq!(density[&coord(4, 5)], 6.0);
+    }
+}
@@ -6,6 +6,8 @@
     that square)
 */
 
+use std::collections::HashSet;
+use std::fmt::{self, Display};
 use std::str::FromStr;
 
 // Itertools for .join() over Iter<Item = String>
@@ -16,19 +18,26 @@ use crate::view::View;
 
 /*
     Coordinates and directions
+
+    A square is written as a letter for the row and a number for the
+    column, counting from 1, so A1 is the top left corner. (The older form,
+    the row and column counting from 0, such as "0 0", is also read.) A
+    direction is written as up, down, left, right, or a diagonal such as
+    down-right.
 */
 
-const BOARD_ROWS: usize = 10;
-const BOARD_COLS: usize = 10;
+/// The largest number of rows or columns a board can have
+pub const MAX_BOARD_SIZE: usize = 26;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Coord {
     row: usize,
     col: usize,
 }
 impl Coord {
+    /// Whether the coordinate is on a board of the largest size
     pub fn is_valid(&self) -> bool {
-        self.row < BOARD_ROWS && self.col < BOARD_COLS
+        self.row < MAX_BOARD_SIZE && self.col < MAX_BOARD_SIZE
     }
     pub fn from_usize(row: usize, col: usize) -> Option<Self> {
         let result = Self { row, col };
@@ -45,6 +54,12 @@ impl Coord {
             None
         }
     }
+    pub fn row(&self) -> usize {
+        self.row
+    }
+    pub fn col(&self) -> usize {
+        self.col
+    }
     fn parse_core(s: &str) -> Option<Self> {
         if let Some(coords) = util::parse_vec_usize(s) {
             if coords.len() == 2 {
@@ -58,11 +73,43 @@ impl Coord {
     }
 }
 
+/// Letter for a row, counting from A
+pub fn row_letter(row: usize) -> char {
+    debug_assert!(row < MAX_BOARD_SIZE);
+    (b'A' + row as u8) as char
+}
+
+impl Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", row_letter(self.row), self.col + 1)
+    }
+}
+
 impl FromStr for Coord {
-    type Err = ();
+    type Err = String;
 
-    fn from_str(s: &str) -> Result<Self, ()> {
-        Coord::parse_core(s).ok_or(())
+    fn from_str(s: &str) -> Result<Self, String> {
+        let err = || {
+            format!(
+                "'{}' isn't a square: give a letter for the row and a number \
+                for the column, such as B7. ",
+                s.trim()
+            )
+        };
+        if let Some(coord) = Coord::parse_core(s) {
+            return Ok(coord);
+        }
+        let s = s.trim();
+        let letter = s.chars().next().ok_or_else(err)?.to_ascii_uppercase();
+        if !letter.is_ascii_uppercase() {
+            return Err(err());
+        }
+        let row = (letter as u8 - b'A') as usize;
+        let col: usize = s[1..].trim().parse().map_err(|_| err())?;
+        if col == 0 {
+            return Err(err());
+        }
+        Coord::from_usize(row, col - 1).ok_or_else(err)
     }
 }
 
@@ -79,6 +126,19 @@ impl Dir {
             && self.dcol >= -1
             && self.dcol <= 1
     }
+    /// All eight valid directions
+    pub fn all() -> impl Iterator<Item = Self> {
+        (-1..=1)
+            .flat_map(|drow| (-1..=1).map(move |dcol| Self { drow, dcol }))
+            .filter(|dir| dir.is_valid())
+    }
+    /// Whether the direction is along a row or column (not diagonal)
+    pub fn is_orthogonal(&self) -> bool {
+        self.drow == 0 || self.dcol == 0
+    }
+    pub fn opposite(self) -> Self {
+        Self { drow: -self.drow, dcol: -self.dcol }
+    }
     pub fn from_isize(drow: isize, dcol: isize) -> Option<Self> {
         let result = Self { drow, dcol };
         if result.is_valid() {
@@ -89,7 +149,50 @@ impl Dir {
     }
 }
 
+impl Dir {
+    /// Name of the direction, e.g. "down-right"
+    fn name(&self) -> String {
+        let vertical = match self.drow {
+            -1 => "up",
+            1 => "down",
+            _ => "",
+        };
+        let horizontal = match self.dcol {
+            -1 => "left",
+            1 => "right",
+            _ => "",
+        };
+        if vertical.is_empty() || horizontal.is_empty() {
+            format!("{}{}", vertical, horizontal)
+        } else {
+            format!("{}-{}", vertical, horizontal)
+        }
+    }
+}
+
+impl Display for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Dir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let name = s.trim().to_lowercase();
+        Dir::all().find(|dir| dir.name() == name).ok_or_else(|| {
+            format!(
+                "'{}' isn't a direction: use up, down, left, right, or a \
+                diagonal such as down-right. ",
+                s.trim()
+            )
+        })
+    }
+}
+
 impl Coord {
+    /// The next coordinate in a direction (which may be off the board)
     fn add(self, dir: Dir) -> Option<Self> {
         let row = (self.row as isize) + dir.drow;
         let col = (self.col as isize) + dir.dcol;
@@ -103,6 +206,230 @@ impl Coord {
     }
 }
 
+/*
+    Board size
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BoardSize {
+    rows: usize,
+    cols: usize,
+}
+impl Default for BoardSize {
+    fn default() -> Self {
+        Self { rows: 10, cols: 10 }
+    }
+}
+impl BoardSize {
+    pub fn new(rows: usize, cols: usize) -> Option<Self> {
+        let sizes = 1..=MAX_BOARD_SIZE;
+        if sizes.contains(&rows) && sizes.contains(&cols) {
+            Some(Self { rows, cols })
+        } else {
+            None
+        }
+    }
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    /// Number of squares
+    pub fn area(&self) -> usize {
+        self.rows * self.cols
+    }
+    pub fn contains(&self, coord: Coord) -> bool {
+        coord.row < self.rows && coord.col < self.cols
+    }
+    /// All coordinates on the board, row by row
+    pub fn coords(self) -> impl Iterator<Item = Coord> {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.cols).map(move |col| Coord { row, col })
+        })
+    }
+    /// The next square in a direction, if it is on the board
+    pub fn step(&self, coord: Coord, dir: Dir) -> Option<Coord> {
+        coord.add(dir).filter(|&next| self.contains(next))
+    }
+    /// The squares of a line of the given length, if it fits on the board
+    pub fn line(
+        &self,
+        coord: Coord,
+        dir: Dir,
+        length: usize,
+    ) -> Option<Vec<Coord>> {
+        if !self.contains(coord) {
+            return None;
+        }
+        let mut result = vec![coord];
+        for _ in 1..length {
+            result.push(self.step(*result.last().unwrap(), dir)?);
+        }
+        Some(result)
+    }
+    /// Squares next to some of the given squares (including at a corner),
+    /// but not among them
+    pub fn neighbors(&self, squares: &[Coord]) -> Vec<Coord> {
+        let mut result = Vec::new();
+        for &coord in squares {
+            for next in Dir::all().filter_map(|dir| self.step(coord, dir)) {
+                if !squares.contains(&next) && !result.contains(&next) {
+                    result.push(next);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl BoardSize {
+    /// A displayed grid (squares separated by spaces, rows by newlines) with
+    /// the rows lettered and the columns numbered
+    pub fn label(&self, grid: &str) -> String {
+        let header: String =
+            (1..=self.cols).map(|col| format!("{:>3}", col)).collect();
+        let mut lines = vec![format!(" {}", header)];
+        for (row, line) in grid.lines().enumerate() {
+            let squares: String =
+                line.split(' ').map(|sq| format!("{:>3}", sq)).collect();
+            lines.push(format!("{}{}", row_letter(row), squares));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Size in the format "8x10" (rows by columns)
+impl Display for BoardSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.rows, self.cols)
+    }
+}
+
+impl FromStr for BoardSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let err = || {
+            format!(
+                "Board size should be rows x columns (e.g. 8x10), each \
+                from 1 to {}. ",
+                MAX_BOARD_SIZE
+            )
+        };
+        let (rows, cols) = s.trim().split_once('x').ok_or_else(err)?;
+        let rows = rows.trim().parse().map_err(|_| err())?;
+        let cols = cols.trim().parse().map_err(|_| err())?;
+        Self::new(rows, cols).ok_or_else(err)
+    }
+}
+
+/*
+    Ships
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShipType {
+    length: usize,
+}
+impl ShipType {
+    pub fn from_usize(length: usize) -> Self {
+        Self { length }
+    }
+    pub fn length(&self) -> usize {
+        self.length
+    }
+    pub fn from_isize(length: isize) -> Option<Self> {
+        if length >= 0 {
+            let length = length as usize;
+            Some(Self { length })
+        } else {
+            None
+        }
+    }
+}
+
+/// Ships are named after the classic fleet, by length
+const SHIP_NAMES: [(usize, &str); 4] =
+    [(2, "destroyer"), (3, "cruiser"), (4, "battleship"), (5, "carrier")];
+
+impl ShipType {
+    /// Name of the ship, if it has one
+    pub fn name(&self) -> Option<&'static str> {
+        SHIP_NAMES
+            .iter()
+            .find(|(length, _)| *length == self.length)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl Display for ShipType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "ship of length {}", self.length),
+        }
+    }
+}
+
+/// A ship given by its name or its length
+impl FromStr for ShipType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let name = s.trim().to_lowercase();
+        if let Ok(length) = name.parse() {
+            return Ok(Self { length });
+        }
+        SHIP_NAMES
+            .iter()
+            .find(|(_, ship)| *ship == name)
+            .map(|&(length, _)| Self { length })
+            .ok_or_else(|| {
+                format!(
+                    "'{}' isn't a ship: give its name (destroyer, cruiser, \
+                    battleship or carrier) or its length. ",
+                    s.trim()
+                )
+            })
+    }
+}
+
+/// A ship on a board: where it is, and which of its squares have been hit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedShip {
+    ship: ShipType,
+    origin: Coord,
+    dir: Dir,
+    hits: HashSet<Coord>,
+}
+impl PlacedShip {
+    pub fn ship(&self) -> ShipType {
+        self.ship
+    }
+    pub fn origin(&self) -> Coord {
+        self.origin
+    }
+    pub fn dir(&self) -> Dir {
+        self.dir
+    }
+    pub fn hits(&self) -> &HashSet<Coord> {
+        &self.hits
+    }
+    /// Squares of the ship, from the origin
+    pub fn squares(&self) -> Vec<Coord> {
+        let mut result = vec![self.origin];
+        for _ in 1..self.ship.length {
+            let next = result.last().unwrap().add(self.dir);
+            result.push(next.expect("ship should fit on the board"));
+        }
+        result
+    }
+    pub fn is_sunk(&self) -> bool {
+        self.hits.len() == self.ship.length
+    }
+}
+
 /*
     Enums for squares in the grid:
     - Square is the ground truth about the cell (on either player's board)
@@ -113,10 +440,13 @@ impl Coord {
 pub enum Square {
     Ship,
     ShipHit,
+    // Square of a ship that has been sunk (the whole ship is then public)
+    ShipSunk,
     Sea,
     SeaMiss,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Square {
     fn default() -> Self {
         Square::Sea
@@ -127,6 +457,21 @@ impl Default for Square {
 pub enum HitResult {
     Hit,
     Miss,
+    // The hit was the last square of the ship left
+    Sunk(ShipType),
+    // The square had been shot at before
+    AlreadyShot,
+}
+
+impl Display for HitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            HitResult::Hit => write!(f, "hit"),
+            HitResult::Miss => write!(f, "miss"),
+            HitResult::Sunk(ship) => write!(f, "hit, and sunk the {}", ship),
+            HitResult::AlreadyShot => write!(f, "already shot there"),
+        }
+    }
 }
 
 impl Square {
@@ -141,7 +486,6 @@ impl Square {
 
     /// Fire a shot at the square
     pub fn shoot(&mut self) -> HitResult {
-        // Returns true if a **new** hit
         match *self {
             Square::Ship => {
                 *self = Square::ShipHit;
@@ -151,7 +495,9 @@ impl Square {
                 *self = Square::SeaMiss;
                 HitResult::Miss
             }
-            Square::ShipHit | Square::SeaMiss => HitResult::Miss,
+            Square::ShipHit | Square::ShipSunk | Square::SeaMiss => {
+                HitResult::AlreadyShot
+            }
         }
     }
 }
@@ -167,6 +513,7 @@ impl View for Square {
         match *self {
             Square::Ship => "s".to_string(),
             Square::ShipHit => "x".to_string(),
+            Square::ShipSunk => "#".to_string(),
             Square::Sea => "-".to_string(),
             Square::SeaMiss => "o".to_string(),
         }
@@ -180,19 +527,35 @@ impl View for Square {
     Core board abstraction
 */
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct Board {
-    grid: [[Square; BOARD_COLS]; BOARD_ROWS],
+    size: BoardSize,
+    grid: Vec<Vec<Square>>,
     ship_remaining: usize,
+    ships: Vec<PlacedShip>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new(BoardSize::default())
+    }
 }
 
 impl Board {
+    /// An empty board of the given size
+    pub fn new(size: BoardSize) -> Self {
+        let grid = vec![vec![Square::Sea; size.cols]; size.rows];
+        Self { size, grid, ship_remaining: 0, ships: Vec::new() }
+    }
+    pub fn size(&self) -> BoardSize {
+        self.size
+    }
     fn get_square(&self, coord: Coord) -> &Square {
-        debug_assert!(coord.is_valid());
+        debug_assert!(self.size.contains(coord));
         &self.grid[coord.row][coord.col]
     }
     fn get_square_mut(&mut self, coord: Coord) -> &mut Square {
-        debug_assert!(coord.is_valid());
+        debug_assert!(self.size.contains(coord));
         &mut self.grid[coord.row][coord.col]
     }
 
@@ -203,31 +566,59 @@ impl Board {
 
     /// Fire a shot at a square on the board
     pub fn shoot(&mut self, coord: Coord) -> HitResult {
-        debug_assert!(coord.is_valid());
+        debug_assert!(self.size.contains(coord));
         let result = self.get_square_mut(coord).shoot();
-        if result == HitResult::Hit {
-            debug_assert!(self.ship_remaining > 0);
-            self.ship_remaining -= 1;
+        if result != HitResult::Hit {
+            return result;
         }
-        result
+        debug_assert!(self.ship_remaining > 0);
+        self.ship_remaining -= 1;
+        let ship = self
+            .ships
+            .iter_mut()
+            .find(|ship| ship.squares().contains(&coord))
+            .expect("every ship square should belong to a ship");
+        ship.hits.insert(coord);
+        if !ship.is_sunk() {
+            return result;
+        }
+        let (ship_type, squares) = (ship.ship, ship.squares());
+        for square in squares {
+            *self.get_square_mut(square) = Square::ShipSunk;
+        }
+        HitResult::Sunk(ship_type)
+    }
+
+    /// Ships placed on the board, in the order they were placed
+    pub fn ships(&self) -> &[PlacedShip] {
+        &self.ships
     }
 
     /// Get ground truth about a square
     pub fn get_priv(&self, coord: Coord) -> Square {
-        debug_assert!(coord.is_valid());
         *self.get_square(coord)
     }
 
     /// Get publicly visible info about a square
     pub fn get_pub(&self, coord: Coord) -> Square {
-        debug_assert!(coord.is_valid());
         self.get_square(coord).hide()
     }
 
+    /// The publicly visible board, with unhit ships hidden
+    pub fn public_view(&self) -> PublicBoard {
+        let mut grid = self.grid.clone();
+        for square in grid.iter_mut().flat_map(|row| row.iter_mut()) {
+            *square = square.hide();
+        }
+        let sunk =
+            self.ships.iter().filter(|ship| ship.is_sunk()).cloned().collect();
+        let ship_remaining = self.ship_remaining;
+        PublicBoard { size: self.size, grid, ship_remaining, sunk }
+    }
+
     /// Place a ship on the board
     /// Returns true if successful
-    pub fn place_ship_square(&mut self, coord: Coord) -> bool {
-        debug_assert!(coord.is_valid());
+    fn place_ship_square(&mut self, coord: Coord) -> bool {
         let square = self.get_square(coord);
         if square == &Square::Ship {
             false
@@ -247,10 +638,12 @@ impl Board {
         dir: Dir,
         length: usize,
     ) -> bool {
-        if !coord.is_valid() || !dir.is_valid() {
-            false
-        } else if length == 0 {
+        // The line may end on the edge of the board, so the square past
+        // its end needn't be on the board
+        if length == 0 {
             true
+        } else if !self.size.contains(coord) || !dir.is_valid() {
+            false
         } else {
             let square = self.get_square(coord);
             if square == &Square::Ship {
@@ -260,25 +653,26 @@ impl Board {
                 let new_len = length - 1;
                 match coord.add(dir) {
                     Some(new) => self.valid_ship_line(new, dir, new_len),
-                    None => false,
+                    None => new_len == 0,
                 }
             }
         }
     }
 
-    /// Place a line of ships on the board
+    /// Place a ship on the board, from origin in direction dir
     /// Returns true if successful
-    pub fn place_ship_line(
+    pub fn place_ship(
         &mut self,
-        mut coord: Coord,
+        ship: ShipType,
+        origin: Coord,
         dir: Dir,
-        length: usize,
     ) -> bool {
-        if self.valid_ship_line(coord, dir, length) {
-            for _ in 0..length {
+        if self.valid_ship_line(origin, dir, ship.length) {
+            let placed = PlacedShip { ship, origin, dir, hits: HashSet::new() };
+            for coord in placed.squares() {
                 assert!(self.place_ship_square(coord));
-                coord = coord.add(dir).unwrap();
             }
+            self.ships.push(placed);
             true
         } else {
             false
@@ -286,12 +680,14 @@ impl Board {
     }
 }
 
-impl View for [Square; BOARD_COLS] {
+impl View for Vec<Square> {
     fn eq_priv(&self, other: &Self) -> bool {
-        (0..BOARD_COLS).all(|i| self[i].eq_priv(&other[i]))
+        self.len() == other.len()
+            && self.iter().zip(other).all(|(a, b)| a.eq_priv(b))
     }
     fn eq_pub(&self, other: &Self) -> bool {
-        (0..BOARD_COLS).all(|i| self[i].eq_pub(&other[i]))
+        self.len() == other.len()
+            && self.iter().zip(other).all(|(a, b)| a.eq_pub(b))
     }
     fn disp_priv(&self) -> String {
         self.iter().map(|square| square.disp_priv()).join(" ")
@@ -303,10 +699,12 @@ impl View for [Square; BOARD_COLS] {
 
 impl View for Board {
     fn eq_priv(&self, other: &Self) -> bool {
-        (0..BOARD_ROWS).all(|i| self.grid[i].eq_priv(&other.grid[i]))
+        self.size == other.size
+            && self.grid.iter().zip(&other.grid).all(|(a, b)| a.eq_priv(b))
     }
     fn eq_pub(&self, other: &Self) -> bool {
-        (0..BOARD_ROWS).all(|i| self.grid[i].eq_pub(&other.grid[i]))
+        self.size == other.size
+            && self.grid.iter().zip(&other.grid).all(|(a, b)| a.eq_pub(b))
     }
     fn disp_priv(&self) -> String {
         self.grid.iter().map(|row| row.disp_priv()).join("\n")
@@ -315,3 +713,47 @@ impl View for Board {
         self.grid.iter().map(|row| row.disp_pub()).join("\n")
     }
 }
+
+/*
+    Public view of a board
+
+    This is what the opponent knows about a board: hits and misses, and
+    the ships that have been sunk, but not the positions of ships that
+    haven't been hit. Since unhit ships are hidden when it is created, they
+    can't be recovered from it.
+*/
+
+#[derive(Debug, Clone)]
+pub struct PublicBoard {
+    size: BoardSize,
+    grid: Vec<Vec<Square>>,
+    ship_remaining: usize,
+    sunk: Vec<PlacedShip>,
+}
+
+impl PublicBoard {
+    /// Get publicly visible info about a square
+    pub fn get(&self, coord: Coord) -> Square {
+        debug_assert!(self.size.contains(coord));
+        self.grid[coord.row][coord.col]
+    }
+
+    pub fn size(&self) -> BoardSize {
+        self.size
+    }
+
+    /// Get remaining ship squares
+    pub fn ship_squares_left(&self) -> usize {
+        self.ship_remaining
+    }
+
+    /// Ships that have been sunk, in the order they were placed
+    pub fn sunk_ships(&self) -> &[PlacedShip] {
+        &self.sunk
+    }
+
+    /// Display the public view
+    pub fn disp(&self) -> String {
+        self.grid.iter().map(|row| row.disp_pub()).join("\n")
+    }
+}
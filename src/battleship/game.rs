@@ -2,37 +2,316 @@
     The game logic
 */
 
-use std::collections::HashSet;
 use std::fmt::{self, Display};
 
-use super::board::{Board, Coord, Dir};
+pub use super::board::ShipType;
+use super::board::{
+    self, Board, BoardSize, Coord, Dir, HitResult, PublicBoard, Square,
+};
+use super::rules::Rules;
 
-use crate::abstract_game::{AbstractGame, Ai, GameStatus};
+use crate::abstract_game::{AbstractGame, GameStatus};
+use crate::ai::ismcts::Determinize;
+use crate::ai::transposition::{self, PositionHash};
+use crate::console::Console;
 use crate::player::TwoPlayers;
+use crate::random::GameRng;
 use crate::util;
 use crate::view::View;
 
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::str::FromStr;
+
 const NUM_PLAYERS: usize = 2;
-const STARTING_SHIPS: &[usize] = &[3, 4, 5];
+const DEFAULT_FLEET: &[usize] = &[5, 4, 3];
+
+/// Boards and fleets that can be chosen by name: the classic Milton
+/// Bradley game, and small boards for trying things out
+const PRESETS: &[(&str, usize, usize, &[usize])] = &[
+    ("classic", 10, 10, &[5, 4, 3, 3, 2]),
+    ("small", 6, 6, &[3, 3, 2]),
+    ("tiny", 4, 4, &[3, 2]),
+];
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct ShipType {
-    length: usize,
+/// Ships with the given lengths, shortest first
+fn ships_of_lengths(lengths: &[usize]) -> Vec<ShipType> {
+    let mut ships: Vec<ShipType> =
+        lengths.iter().map(|&len| ShipType::from_usize(len)).collect();
+    ships.sort();
+    ships
 }
-impl ShipType {
-    pub fn from_usize(length: usize) -> Self {
-        Self { length }
+
+/// The ships of a fleet that haven't been sunk on a board, shortest first
+pub fn ships_afloat(ships: &[ShipType], view: &PublicBoard) -> Vec<ShipType> {
+    let mut afloat = ships.to_vec();
+    afloat.sort();
+    for sunk in view.sunk_ships() {
+        if let Some(i) = afloat.iter().position(|&ship| ship == sunk.ship()) {
+            afloat.remove(i);
+        }
     }
-    pub fn from_isize(length: isize) -> Option<Self> {
-        if length >= 0 {
-            let length = length as usize;
-            Some(Self { length })
-        } else {
-            None
+    afloat
+}
+
+/// Number of boards the search for a placement looks at before it starts
+/// again in a fresh random order (with twice as many each time)
+const PLACEMENT_STEPS: usize = 1000;
+
+/// A board with the given fleet placed at random under the rules,
+/// consistent with a public view of it: the sunk ships are where they
+/// sank, every other hit is on a ship that is still afloat, and no ship is
+/// on a miss. The shots in the
+/// view are then made again, so the new board has the same public view.
+/// None if there is no such board.
+/// (Ships are placed over the hits first, so the boards are not drawn
+/// uniformly, but every consistent board can be drawn.)
+pub fn sample_board(
+    view: &PublicBoard,
+    ships: &[ShipType],
+    rules: &Rules,
+    rng: &mut GameRng,
+) -> Option<Board> {
+    let mut board = Board::new(view.size());
+    for sunk in view.sunk_ships() {
+        assert!(board.place_ship(sunk.ship(), sunk.origin(), sunk.dir()));
+    }
+    let ships = ships_afloat(ships, view);
+    // A search that runs out of steps may just have made a bad choice
+    // early on, so it is tried again; one that doesn't has ruled out every
+    // board
+    let mut max_steps = PLACEMENT_STEPS;
+    let mut board = loop {
+        let mut placer =
+            Placer { view, rules, rng: Some(&mut *rng), steps_left: max_steps };
+        let uncovered = uncovered_hits(view, &board);
+        if let Some(board) = placer.place(board.clone(), &ships, &uncovered) {
+            break board;
+        }
+        if placer.steps_left > 0 {
+            return None;
+        }
+        max_steps *= 2;
+    };
+    for coord in view.size().coords() {
+        if view.get(coord) != Square::Sea {
+            board.shoot(coord);
         }
     }
+    Some(board)
 }
 
+/// For each ship placed on a board (with others) to make a second board,
+/// the board with the others placed: a ship of the same kind placed
+/// alongside them leaves room for them
+fn boards_alongside(board: &Board, placed: &Board) -> Vec<(ShipType, Board)> {
+    let new_ships = &placed.ships()[board.ships().len()..];
+    let mut result = Vec::new();
+    for (i, left_out) in new_ships.iter().enumerate() {
+        let mut others = board.clone();
+        for (j, ship) in new_ships.iter().enumerate() {
+            if j != i {
+                assert!(others.place_ship(
+                    ship.ship(),
+                    ship.origin(),
+                    ship.dir()
+                ));
+            }
+        }
+        result.push((left_out.ship(), others));
+    }
+    result
+}
+
+/// Whether ships can all still be placed on a board (on which no shots
+/// have been fired) under the rules
+pub fn can_place_all(board: &Board, ships: &[ShipType], rules: &Rules) -> bool {
+    ships.is_empty() || place_all(board, ships, rules).is_some()
+}
+
+/// The board with ships all placed on it (the first way found) under the
+/// rules, if they can be
+fn place_all(
+    board: &Board,
+    ships: &[ShipType],
+    rules: &Rules,
+) -> Option<Board> {
+    let view = board.public_view();
+    let mut placer =
+        Placer { view: &view, rules, rng: None, steps_left: usize::MAX };
+    placer.place(board.clone(), ships, &[])
+}
+
+fn is_uncovered_hit(view: &PublicBoard, board: &Board, coord: Coord) -> bool {
+    view.get(coord) == Square::ShipHit && board.get_priv(coord) == Square::Sea
+}
+
+/// Hits in a view not yet covered by a ship on the board
+fn uncovered_hits(view: &PublicBoard, board: &Board) -> Vec<Coord> {
+    let coords = view.size().coords();
+    coords.filter(|&c| is_uncovered_hit(view, board, c)).collect()
+}
+
+/// Placements of a ship on a board allowed by the rules and consistent
+/// with a public view of the board
+fn consistent_placements<'a>(
+    view: &'a PublicBoard,
+    rules: &'a Rules,
+    board: &'a Board,
+    ship: ShipType,
+) -> impl Iterator<Item = (Coord, Dir)> + 'a {
+    let size = view.size();
+    // Ships along rows and columns pack together best, so a search that
+    // tries placements in order does best to try those first
+    let mut dirs: Vec<Dir> = rules.dirs().collect();
+    dirs.sort_by_key(|dir| !dir.is_orthogonal());
+    size.coords()
+        .flat_map(move |coord| {
+            dirs.clone().into_iter().map(move |dir| (coord, dir))
+        })
+        .filter(move |&(coord, dir)| {
+            if !rules.allows_placement(board, ship, coord, dir) {
+                return false;
+            }
+            let squares = size.line(coord, dir, ship.length()).unwrap();
+            if squares.iter().any(|&c| view.get(c) == Square::SeaMiss)
+                || !rules.allows_hidden_placement(view, dir, &squares)
+            {
+                return false;
+            }
+            let hits = squares
+                .iter()
+                .filter(|&&c| is_uncovered_hit(view, board, c))
+                .count();
+            // A ship that is still afloat can't have been hit everywhere
+            hits < ship.length()
+        })
+}
+
+/// Search (by backtracking) for a placement of ships on a board that
+/// covers the hits in a view. While there are hits to cover, each step
+/// covers the one with the fewest placements through it, and a board is
+/// given up on as soon as a hit or a ship has no placement left; the
+/// ships left over are then placed longest first.
+struct Placer<'a> {
+    view: &'a PublicBoard,
+    rules: &'a Rules,
+    // Placements are tried in random order if there is an rng, and in
+    // order otherwise (so that the first that works is found quickly)
+    rng: Option<&'a mut GameRng>,
+    // Boards the search may still look at before it gives up
+    steps_left: usize,
+}
+
+impl Placer<'_> {
+    /// The board with the ships placed, or None if there is no placement
+    /// (or the search runs out of steps), given the hits still uncovered
+    fn place(
+        &mut self,
+        board: Board,
+        ships: &[ShipType],
+        uncovered: &[Coord],
+    ) -> Option<Board> {
+        if self.steps_left == 0 {
+            return None;
+        }
+        self.steps_left -= 1;
+        let (view, rules) = (self.view, self.rules);
+        let size = view.size();
+        let ship_squares: usize = ships.iter().map(|ship| ship.length()).sum();
+        if uncovered.len() > ship_squares {
+            return None;
+        }
+        if ships.is_empty() {
+            return Some(board);
+        }
+        let with_ship = |ship: ShipType| {
+            move |(coord, dir): (Coord, Dir)| (ship, coord, dir)
+        };
+        if uncovered.is_empty() {
+            // Only open sea is left to place ships in, so place the longest
+            let ship = *ships.iter().max().unwrap();
+            let placements = consistent_placements(view, rules, &board, ship)
+                .map(with_ship(ship));
+            return match self.rng.as_deref_mut() {
+                Some(rng) => {
+                    let mut placements: Vec<_> = placements.collect();
+                    placements.shuffle(rng);
+                    self.place_each(&board, ships, uncovered, placements)
+                }
+                None => self.place_each(&board, ships, uncovered, placements),
+            };
+        }
+        let mut kinds = ships.to_vec();
+        kinds.sort();
+        kinds.dedup();
+        let mut all = Vec::new();
+        for ship in kinds {
+            let len = all.len();
+            all.extend(
+                consistent_placements(view, rules, &board, ship)
+                    .map(with_ship(ship)),
+            );
+            if all.len() == len {
+                return None;
+            }
+        }
+        let mut by_hit = Vec::new();
+        for &hit in uncovered {
+            let through: Vec<(ShipType, Coord, Dir)> = all
+                .iter()
+                .filter(|&&(ship, coord, dir)| {
+                    let squares = size.line(coord, dir, ship.length());
+                    squares.unwrap().contains(&hit)
+                })
+                .copied()
+                .collect();
+            if through.is_empty() {
+                return None;
+            }
+            by_hit.push(through);
+        }
+        let mut candidates = by_hit.into_iter().min_by_key(Vec::len).unwrap();
+        if let Some(rng) = self.rng.as_deref_mut() {
+            candidates.shuffle(rng);
+        }
+        self.place_each(&board, ships, uncovered, candidates)
+    }
+
+    /// The board with the ships placed, trying each candidate placement
+    /// in turn for the first ship placed
+    fn place_each(
+        &mut self,
+        board: &Board,
+        ships: &[ShipType],
+        uncovered: &[Coord],
+        candidates: impl IntoIterator<Item = (ShipType, Coord, Dir)>,
+    ) -> Option<Board> {
+        for (ship, coord, dir) in candidates {
+            let mut next = board.clone();
+            assert!(next.place_ship(ship, coord, dir));
+            let mut rest = ships.to_vec();
+            let i = rest.iter().position(|&s| s == ship).unwrap();
+            rest.remove(i);
+            let still_uncovered: Vec<Coord> = uncovered
+                .iter()
+                .copied()
+                .filter(|&c| next.get_priv(c) == Square::Sea)
+                .collect();
+            if let Some(board) = self.place(next, &rest, &still_uncovered) {
+                return Some(board);
+            }
+            if self.steps_left == 0 {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// A move: a ship placed from a square in a direction, such as
+/// "carrier A1 down" or "5 A1 down", or a square to shoot at, such as "B7".
+/// The older numeric form ("5 0 0 1 0", or "1 6" for a shot) is also read.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Move {
     PlaceShip(ShipType, Coord, Dir),
@@ -47,6 +326,7 @@ impl Move {
             Move::Shoot(coord) => coord.is_valid(),
         }
     }
+    /// Parse the older numeric form
     fn parse_core(s: &str) -> Option<Self> {
         let coords = util::parse_vec_isize(s)?;
         if coords.len() == 5 {
@@ -69,21 +349,303 @@ impl Move {
 impl Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            Move::PlaceShip(ship, coord, dir) => {
-                write!(f, "Place({:?}, {:?}, {:?})", ship, coord, dir)
+            Move::PlaceShip(ship, coord, dir) => match ship.name() {
+                Some(name) => write!(f, "{} {} {}", name, coord, dir),
+                None => write!(f, "{} {} {}", ship.length(), coord, dir),
+            },
+            Move::Shoot(coord) => write!(f, "{}", coord),
+        }
+    }
+}
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        if let Some(mv) = Move::parse_core(s) {
+            return Ok(mv);
+        }
+        match s.split_whitespace().collect::<Vec<&str>>()[..] {
+            [coord] => Ok(Move::Shoot(coord.parse()?)),
+            [ship, coord, dir] => {
+                Ok(Move::PlaceShip(ship.parse()?, coord.parse()?, dir.parse()?))
             }
-            Move::Shoot(coord) => {
-                write!(f, "Shoot({:?})", coord)
+            _ => Err("Give a square to shoot at, such as B7, or a ship, \
+                square and direction to place a ship, such as carrier A1 \
+                down. "
+                .to_string()),
+        }
+    }
+}
+
+/// Who fires the first shot, once both fleets are placed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FirstShot {
+    Player(TwoPlayers),
+    /// Drawn at random when the game is set up
+    Random,
+}
+impl Display for FirstShot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FirstShot::Player(plyr) => write!(f, "{}", plyr.as_index() + 1),
+            FirstShot::Random => write!(f, "random"),
+        }
+    }
+}
+impl FromStr for FirstShot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "1" => Ok(FirstShot::Player(TwoPlayers::ONE)),
+            "2" => Ok(FirstShot::Player(TwoPlayers::TWO)),
+            "random" => Ok(FirstShot::Random),
+            _ => {
+                Err("Who fires first should be 1, 2 or 'random'. ".to_string())
+            }
+        }
+    }
+}
+
+/// A preset board and fleet, by name
+fn preset(name: &str) -> Result<(BoardSize, Vec<ShipType>), String> {
+    let (_, rows, cols, lengths) = PRESETS
+        .iter()
+        .find(|(preset, _, _, _)| *preset == name.trim())
+        .ok_or_else(|| {
+            let names: Vec<&str> = PRESETS.iter().map(|p| p.0).collect();
+            format!(
+                "Unknown preset: {} (the presets are {}). ",
+                name.trim(),
+                names.join(", ")
+            )
+        })?;
+    let size = BoardSize::new(*rows, *cols).expect("preset should fit");
+    Ok((size, ships_of_lengths(lengths)))
+}
+
+/// A fleet given by the lengths of its ships, e.g. "5 4 3 3 2"
+fn parse_fleet(s: &str) -> Result<Vec<ShipType>, String> {
+    let lengths = util::parse_vec_usize(s).ok_or_else(|| {
+        "The fleet should be a list of ship lengths, e.g. 5 4 3 3 2. "
+            .to_string()
+    })?;
+    Ok(ships_of_lengths(&lengths))
+}
+
+/// A board and fleet, as a preset name or a size followed by ship lengths,
+/// e.g. "classic" or "8x8 4 3 3"
+fn parse_layout(s: &str) -> Result<(BoardSize, Vec<ShipType>), String> {
+    match s.trim().split_once(' ') {
+        Some((size, fleet)) if size.contains('x') => {
+            Ok((size.parse()?, parse_fleet(fleet)?))
+        }
+        _ => preset(s),
+    }
+}
+
+/// Setup parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BattleshipParams {
+    pub first_shot: FirstShot,
+    pub size: BoardSize,
+    /// Ships each player places, shortest first
+    pub fleet: Vec<ShipType>,
+    pub rules: Rules,
+}
+impl Default for BattleshipParams {
+    fn default() -> Self {
+        Self {
+            first_shot: FirstShot::Player(TwoPlayers::ONE),
+            size: BoardSize::default(),
+            fleet: ships_of_lengths(DEFAULT_FLEET),
+            rules: Rules::default(),
+        }
+    }
+}
+impl BattleshipParams {
+    /// Check that the fleet fits on the board: every ship fits along a row
+    /// or column, the ships cover at most half of the board, and they can
+    /// all be placed under the rules. When ships can't touch, each ship
+    /// also needs the row or column of sea beside it and the square past
+    /// its end.
+    pub fn check(&self) -> Result<(), String> {
+        if self.fleet.is_empty() {
+            return Err("The fleet needs at least one ship. ".to_string());
+        }
+        let longest = self.size.rows().max(self.size.cols());
+        for ship in &self.fleet {
+            if ship.length() == 0 || ship.length() > longest {
+                return Err(format!(
+                    "A ship of length {} doesn't fit on a {} board. ",
+                    ship.length(),
+                    self.size
+                ));
             }
         }
+        let squares: usize = self.fleet.iter().map(|s| s.length()).sum();
+        if squares * 2 > self.size.area() {
+            return Err(format!(
+                "The fleet covers {} squares, but ships can cover at most \
+                half of a {} board ({} squares). ",
+                squares,
+                self.size,
+                self.size.area() / 2
+            ));
+        }
+        let spaced: usize =
+            self.fleet.iter().map(|s| (s.length() + 1) * 2).sum();
+        let (rows, cols) = (self.size.rows(), self.size.cols());
+        if self.rules.no_touching && spaced * 2 > (rows + 1) * (cols + 1) {
+            return Err(format!(
+                "A {} board is too crowded for this fleet when ships can't \
+                touch. ",
+                self.size
+            ));
+        }
+        if !can_place_all(&Board::new(self.size), &self.fleet, &self.rules) {
+            return Err(format!(
+                "This fleet can't be placed on a {} board under these \
+                rules. ",
+                self.size
+            ));
+        }
+        Ok(())
+    }
+}
+/// Parameters in the format "first 2; board 8x8; fleet 4 3 3; rules salvo",
+/// listing only those that differ from the defaults ("preset classic" can
+/// also be given, for a preset board and fleet)
+impl Display for BattleshipParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let default = Self::default();
+        let mut parts = Vec::new();
+        if self.first_shot != default.first_shot {
+            parts.push(format!("first {}", self.first_shot));
+        }
+        if self.size != default.size {
+            parts.push(format!("board {}", self.size));
+        }
+        if self.fleet != default.fleet {
+            let lengths: Vec<String> = self
+                .fleet
+                .iter()
+                .rev()
+                .map(|s| s.length().to_string())
+                .collect();
+            parts.push(format!("fleet {}", lengths.join(" ")));
+        }
+        if self.rules != default.rules {
+            parts.push(format!("rules {}", self.rules));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+impl FromStr for BattleshipParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        for part in s.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, arg) = part.split_once(' ').unwrap_or((part, ""));
+            match name {
+                "first" => params.first_shot = arg.parse()?,
+                "board" => params.size = arg.parse()?,
+                "fleet" => params.fleet = parse_fleet(arg)?,
+                "preset" => (params.size, params.fleet) = preset(arg)?,
+                "rules" => params.rules = arg.parse()?,
+                _ => return Err(format!("Unknown setting: {}. ", part)),
+            }
+        }
+        params.check()?;
+        Ok(params)
     }
 }
 
-#[derive(Debug)]
+/// A shot and what it hit, which both players are told
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Shot {
+    pub shooter: TwoPlayers,
+    pub coord: Coord,
+    pub result: HitResult,
+}
+impl Shot {
+    /// Report of the shot to a player
+    fn report(&self, plyr: TwoPlayers) -> String {
+        let shooter = if self.shooter == plyr {
+            "You".to_string()
+        } else {
+            self.shooter.to_string()
+        };
+        format!("{} fired at {}: {}", shooter, self.coord, self.result)
+    }
+}
+
+/// What a player can see: their own board in full, the public view of
+/// the opponent's board, the fleet each player has and the rules, the
+/// ships they have left to place, the shots fired in each player's last
+/// turn, and how many shots are left in this turn
+#[derive(Debug, Clone)]
+pub struct Observation {
+    own_board: Board,
+    opponent_board: PublicBoard,
+    fleet: Vec<ShipType>,
+    rules: Rules,
+    pending: Vec<ShipType>,
+    recent_shots: Vec<Shot>,
+    shots_left: usize,
+    to_move: TwoPlayers,
+}
+
+impl Observation {
+    pub fn own_board(&self) -> &Board {
+        &self.own_board
+    }
+    pub fn opponent_board(&self) -> &PublicBoard {
+        &self.opponent_board
+    }
+    /// Ships each player places, shortest first
+    pub fn fleet(&self) -> &[ShipType] {
+        &self.fleet
+    }
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// Ships left to place, shortest first
+    pub fn pending(&self) -> &[ShipType] {
+        &self.pending
+    }
+    /// The shots fired in each player's last turn, in the order they were
+    /// fired
+    pub fn recent_shots(&self) -> &[Shot] {
+        &self.recent_shots
+    }
+    /// Shots the player to shoot has left in this turn
+    pub fn shots_left(&self) -> usize {
+        self.shots_left
+    }
+    /// Player to shoot next, once the fleets are placed
+    pub fn to_move(&self) -> TwoPlayers {
+        self.to_move
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct GameState {
+    // Player to shoot next, once the fleets are placed (player 1 places
+    // their fleet first, then player 2)
     to_move: TwoPlayers,
-    pending_placement: [HashSet<ShipType>; NUM_PLAYERS],
+    // Ships each player places, shortest first
+    fleet: Vec<ShipType>,
+    rules: Rules,
+    // Ships left to place, shortest first
+    pending_placement: [Vec<ShipType>; NUM_PLAYERS],
     boards: [Board; NUM_PLAYERS],
+    // The shots fired in each player's last turn, in the order they were
+    // fired
+    recent_shots: Vec<Shot>,
+    // Shots to_move has left in this turn
+    shots_left: usize,
 }
 
 impl GameState {
@@ -93,56 +655,187 @@ impl GameState {
     fn get_board_mut(&mut self, plyr: TwoPlayers) -> &mut Board {
         &mut self.boards[plyr.as_index()]
     }
-    fn get_pending(&self, plyr: TwoPlayers) -> &HashSet<ShipType> {
+    fn size(&self) -> BoardSize {
+        self.boards[0].size()
+    }
+    fn get_pending(&self, plyr: TwoPlayers) -> &[ShipType] {
         &self.pending_placement[plyr.as_index()]
     }
-    fn get_pending_mut(&mut self, plyr: TwoPlayers) -> &mut HashSet<ShipType> {
+    fn get_pending_mut(&mut self, plyr: TwoPlayers) -> &mut Vec<ShipType> {
         &mut self.pending_placement[plyr.as_index()]
     }
     fn print_pending(&self, plyr: TwoPlayers) -> String {
         let mut result = String::new();
         for &ship in self.get_pending(plyr).iter() {
-            result.push_str(&format!("{} ", ship.length));
+            result.push_str(&format!("{} ", ship.length()));
         }
         result
     }
-    fn no_pending_placements(&self) -> bool {
-        self.pending_placement.iter().all(|set| set.is_empty())
+    /// The ships a player has afloat, e.g. "cruiser, carrier"
+    fn print_afloat(&self, plyr: TwoPlayers) -> String {
+        let view = self.get_board(plyr).public_view();
+        let afloat = ships_afloat(&self.fleet, &view);
+        if afloat.is_empty() {
+            "none".to_string()
+        } else {
+            let names: Vec<String> =
+                afloat.iter().map(ShipType::to_string).collect();
+            names.join(", ")
+        }
     }
-    fn is_valid_move_core(&self, mv: &Move) -> bool {
-        let plyr = self.cur_player().unwrap();
-        debug_assert_eq!(self.status(), GameStatus::ToMove(plyr));
-        match *mv {
-            Move::PlaceShip(ship, coord, dir) => {
-                let len = ship.length;
-                self.get_pending(plyr).contains(&ship)
-                    && coord.is_valid()
-                    && dir.is_valid()
-                    && self.get_board(plyr).valid_ship_line(coord, dir, len)
-            }
-            Move::Shoot(coord) => {
-                self.no_pending_placements() && coord.is_valid()
+    /// Number of shots a player fires in a turn (not counting extra shots)
+    fn shots_per_turn(&self, plyr: TwoPlayers) -> usize {
+        let ships = self.get_board(plyr).ships();
+        let afloat = ships.iter().filter(|ship| !ship.is_sunk()).count();
+        self.rules.shots_per_turn(afloat)
+    }
+    /// A player's board with a ship placed and then the rest of their
+    /// fleet (the first way found), if that leaves room for the rest (with
+    /// ships that can't touch, a ship placed badly may not)
+    fn room_left(
+        &self,
+        plyr: TwoPlayers,
+        ship: ShipType,
+        coord: Coord,
+        dir: Dir,
+    ) -> Option<Board> {
+        let mut board = self.get_board(plyr).clone();
+        assert!(board.place_ship(ship, coord, dir));
+        let mut rest = self.get_pending(plyr).to_vec();
+        let i = rest.iter().position(|&s| s == ship).unwrap();
+        rest.remove(i);
+        place_all(&board, &rest, &self.rules)
+    }
+    /// Placements of a player's ships that are allowed and leave room for
+    /// the rest of their fleet
+    fn placements(&self, plyr: TwoPlayers) -> Vec<Move> {
+        let board = self.get_board(plyr);
+        let pending = self.get_pending(plyr);
+        // A way of placing the whole fleet left shows that a ship placed
+        // alongside the others in it leaves room for them, so the search
+        // is only needed for ships that don't fit alongside the last few
+        // ways found
+        let mut alongside: Vec<Vec<(ShipType, Board)>> = Vec::new();
+        if let Some(placed) = place_all(board, pending, &self.rules) {
+            alongside.push(boards_alongside(board, &placed));
+        }
+        let mut ships = pending.to_vec();
+        ships.dedup();
+        let mut result = Vec::new();
+        for ship in ships {
+            for coord in self.size().coords() {
+                for dir in self.rules.dirs() {
+                    if !self.rules.allows_placement(board, ship, coord, dir) {
+                        continue;
+                    }
+                    let fits =
+                        alongside.iter().rev().flatten().any(|(s, others)| {
+                            *s == ship
+                                && self
+                                    .rules
+                                    .allows_placement(others, ship, coord, dir)
+                        });
+                    if !fits {
+                        match self.room_left(plyr, ship, coord, dir) {
+                            Some(placed) => {
+                                alongside
+                                    .push(boards_alongside(board, &placed));
+                            }
+                            None => continue,
+                        }
+                    }
+                    result.push(Move::PlaceShip(ship, coord, dir));
+                }
             }
         }
+        result
+    }
+    fn no_pending_placements(&self) -> bool {
+        self.pending_placement.iter().all(|set| set.is_empty())
     }
 }
 
 impl AbstractGame<NUM_PLAYERS> for GameState {
+    const NAME: &'static str = "battleship";
+
     type Move = Move;
-    type SetupParams = ();
+    type SetupParams = BattleshipParams;
+    type Observation = Observation;
 
-    /// Parse setup parameters
-    fn setup_from_user_input() {}
+    fn setup_from_user_input(console: &mut dyn Console) -> BattleshipParams {
+        let (size, fleet) = util::from_user_input_parsing(
+            console,
+            "Board and fleet? Press enter for a 10x10 board with ships \
+            5 4 3, type 'classic', 'small' or 'tiny', or give a size and \
+            ship lengths (e.g. 8x8 4 3 3): ",
+            |raw| {
+                let default = BattleshipParams::default();
+                let (size, fleet) = match raw.trim() {
+                    "" => (default.size, default.fleet),
+                    raw => parse_layout(raw)?,
+                };
+                let params = BattleshipParams { size, fleet, ..default };
+                params.check()?;
+                Ok((params.size, params.fleet))
+            },
+        );
+        let first_shot = util::from_user_input_parsing(
+            console,
+            "Who fires first? Press enter for player 1, or type '2' or \
+            'random': ",
+            |raw| match raw.trim() {
+                "" => Ok(BattleshipParams::default().first_shot),
+                raw => raw.parse(),
+            },
+        );
+        let rules = util::from_user_input_parsing(
+            console,
+            "Rule variants? Press enter for none, or list any of \
+            'orthogonal', 'no-touching', 'salvo' and 'extra-shot': ",
+            |raw| {
+                let rules = raw.parse()?;
+                let params = BattleshipParams {
+                    size,
+                    fleet: fleet.clone(),
+                    rules,
+                    ..Default::default()
+                };
+                params.check()?;
+                Ok(rules)
+            },
+        );
+        BattleshipParams { first_shot, size, fleet, rules }
+    }
 
-    fn game_setup(_params: ()) -> Self {
-        let to_move = TwoPlayers::ONE;
-        let ships: HashSet<ShipType> = STARTING_SHIPS
-            .iter()
-            .map(|&len| ShipType { length: len })
-            .collect();
-        let pending_placement = [ships.clone(), ships];
-        let boards = [Default::default(), Default::default()];
-        Self { to_move, pending_placement, boards }
+    fn format_params(params: &BattleshipParams) -> String {
+        params.to_string()
+    }
+
+    fn parse_params(raw: &str) -> Result<BattleshipParams, String> {
+        raw.parse()
+    }
+
+    fn game_setup(params: BattleshipParams, rng: &mut GameRng) -> Self {
+        let to_move = match params.first_shot {
+            FirstShot::Player(plyr) => plyr,
+            FirstShot::Random if rng.gen_bool(0.5) => TwoPlayers::ONE,
+            FirstShot::Random => TwoPlayers::TWO,
+        };
+        let mut fleet = params.fleet;
+        fleet.sort();
+        let pending_placement = [fleet.clone(), fleet.clone()];
+        let boards = [Board::new(params.size), Board::new(params.size)];
+        // Every ship is afloat at the first shot
+        let shots_left = params.rules.shots_per_turn(fleet.len());
+        Self {
+            to_move,
+            fleet,
+            rules: params.rules,
+            pending_placement,
+            boards,
+            recent_shots: Vec::new(),
+            shots_left,
+        }
     }
 
     fn status(&self) -> GameStatus<NUM_PLAYERS> {
@@ -150,8 +843,9 @@ impl AbstractGame<NUM_PLAYERS> for GameState {
             GameStatus::ToMove(TwoPlayers::ONE)
         } else if !self.pending_placement[1].is_empty() {
             GameStatus::ToMove(TwoPlayers::TWO)
+        } else if self.boards.iter().all(|b| b.ship_squares_left() == 0) {
+            GameStatus::Draw
         } else if self.boards[0].ship_squares_left() == 0 {
-            debug_assert!(self.boards[1].ship_squares_left() > 0);
             GameStatus::Won(TwoPlayers::TWO)
         } else if self.boards[1].ship_squares_left() == 0 {
             GameStatus::Won(TwoPlayers::ONE)
@@ -160,62 +854,854 @@ impl AbstractGame<NUM_PLAYERS> for GameState {
         }
     }
     fn query(&self) -> String {
-        // TODO: make more helpful
-        "Move: ".to_string()
+        match self.cur_player() {
+            Some(plyr) if !self.get_pending(plyr).is_empty() => {
+                "Place a ship (e.g. carrier A1 down): ".to_string()
+            }
+            _ => "Fire at a square (e.g. B7): ".to_string(),
+        }
     }
     fn parse_move(&self, raw: &str) -> Result<Move, String> {
-        // TODO: make this more helpful
-        Move::parse_core(raw)
-            .ok_or_else(|| "Could not parse move. ".to_string())
+        raw.parse()
     }
     fn check_move(&self, mv: &Move) -> Result<(), String> {
-        // TODO: make this more helpful
-        if self.is_valid_move_core(mv) {
-            Ok(())
-        } else {
-            Err("Invalid move".to_string())
+        let plyr = match self.cur_player() {
+            Some(plyr) => plyr,
+            None => return Err("The game is over. ".to_string()),
+        };
+        debug_assert_eq!(self.status(), GameStatus::ToMove(plyr));
+        let placing = !self.get_pending(plyr).is_empty();
+        match *mv {
+            Move::PlaceShip(..) if !placing => Err("Your ships are all \
+                placed: fire at a square, such as B7. "
+                .to_string()),
+            Move::PlaceShip(ship, _, _)
+                if !self.get_pending(plyr).contains(&ship) =>
+            {
+                Err(format!(
+                    "You have no {} left to place (ship lengths left: {}). ",
+                    ship,
+                    self.print_pending(plyr).trim()
+                ))
+            }
+            Move::PlaceShip(ship, coord, dir) => {
+                let board = self.get_board(plyr);
+                self.rules.check_placement(board, ship, coord, dir)?;
+                if self.room_left(plyr, ship, coord, dir).is_some() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "The {} can't go there: that would leave no room \
+                        for the rest of your fleet. ",
+                        ship
+                    ))
+                }
+            }
+            Move::Shoot(_) if placing => Err("Place your ships before \
+                firing. "
+                .to_string()),
+            Move::Shoot(coord) if !self.size().contains(coord) => {
+                let last = board::row_letter(self.size().rows() - 1);
+                Err(format!(
+                    "{} is off the board: the rows go from A to {}, and \
+                    the columns from 1 to {}. ",
+                    coord,
+                    last,
+                    self.size().cols()
+                ))
+            }
+            Move::Shoot(_) => Ok(()),
         }
     }
+    fn legal_moves(&self) -> Vec<Move> {
+        let plyr = match self.cur_player() {
+            Some(plyr) => plyr,
+            None => return Vec::new(),
+        };
+        let mut result = self.placements(plyr);
+        if self.no_pending_placements() {
+            result.extend(self.size().coords().map(Move::Shoot));
+        }
+        result
+    }
     fn make_move(&mut self, mv: Move) {
         let plyr = self.cur_player().unwrap();
         debug_assert!(self.is_valid_move(&mv));
         match mv {
             Move::PlaceShip(ship, coord, dir) => {
-                let len = ship.length;
                 let board = self.get_board_mut(plyr);
-                assert!(board.place_ship_line(coord, dir, len));
-                self.get_pending_mut(plyr).remove(&ship);
+                assert!(board.place_ship(ship, coord, dir));
+                let pending = self.get_pending_mut(plyr);
+                let i = pending.iter().position(|&s| s == ship).unwrap();
+                pending.remove(i);
             }
             Move::Shoot(coord) => {
-                self.get_board_mut(plyr).shoot(coord);
+                let other = plyr.opponent();
+                let result = self.get_board_mut(other).shoot(coord);
+                // The first shot of a turn replaces the player's shots from
+                // their last turn
+                let last_shooter = self.recent_shots.last().map(|s| s.shooter);
+                if last_shooter != Some(plyr) {
+                    self.recent_shots.retain(|shot| shot.shooter != plyr);
+                }
+                self.recent_shots.push(Shot { shooter: plyr, coord, result });
+                let hit = matches!(result, HitResult::Hit | HitResult::Sunk(_));
+                if !(hit && self.rules.extra_shot) {
+                    self.shots_left -= 1;
+                }
+                if self.shots_left == 0 {
+                    self.to_move = other;
+                    self.shots_left = self.shots_per_turn(other);
+                }
             }
         }
     }
     fn print_state_visible(&self, plyr: TwoPlayers) -> String {
         if self.get_pending(plyr).is_empty() {
             let other = plyr.opponent();
-            format!(
+            let mut result = format!(
                 "=== Your Board ===\n{}\n=== Shots ===\n{}\n",
-                self.get_board(plyr).disp_priv(),
-                self.get_board(other).disp_pub(),
-            )
+                self.size().label(&self.get_board(plyr).disp_priv()),
+                self.size().label(&self.get_board(other).disp_pub()),
+            );
+            for shot in &self.recent_shots {
+                result.push_str(&format!("{}\n", shot.report(plyr)));
+            }
+            result.push_str(&format!(
+                "Your ships afloat: {}\n{}'s ships afloat: {}\n",
+                self.print_afloat(plyr),
+                other,
+                self.print_afloat(other),
+            ));
+            if self.to_move == plyr && self.shots_left > 1 {
+                let shots = self.shots_left;
+                result.push_str(&format!(
+                    "You have {} shots left this turn\n",
+                    shots
+                ));
+            }
+            result
         } else {
             format!(
                 "=== Your Board ===\n{}\n=== Ships to Place ===\n{}\n",
-                self.get_board(plyr).disp_priv(),
+                self.size().label(&self.get_board(plyr).disp_priv()),
                 self.print_pending(plyr),
             )
         }
     }
+    fn observe(&self, plyr: TwoPlayers) -> Observation {
+        Observation {
+            own_board: self.get_board(plyr).clone(),
+            opponent_board: self.get_board(plyr.opponent()).public_view(),
+            fleet: self.fleet.clone(),
+            rules: self.rules,
+            pending: self.get_pending(plyr).to_vec(),
+            recent_shots: self.recent_shots.clone(),
+            shots_left: self.shots_left,
+            to_move: self.to_move,
+        }
+    }
 }
 
-pub struct UnimplementedBattleshipAi {}
+impl Determinize<NUM_PLAYERS> for GameState {
+    fn determinize(
+        obs: &Observation,
+        plyr: TwoPlayers,
+        rng: &mut GameRng,
+    ) -> Self {
+        // Player 1 places their ships first, so the opponent has placed
+        // theirs unless player 1 is still placing
+        let opponent_placed = plyr == TwoPlayers::TWO || obs.pending.is_empty();
+        let (opponent_board, opponent_pending) = if opponent_placed {
+            let (view, rules) = (&obs.opponent_board, &obs.rules);
+            let board = sample_board(view, &obs.fleet, rules, rng)
+                .expect("the observation should be consistent with a fleet");
+            (board, Vec::new())
+        } else {
+            (Board::new(obs.own_board.size()), obs.fleet.clone())
+        };
+        let mut boards = [obs.own_board.clone(), opponent_board];
+        let mut pending_placement = [obs.pending.clone(), opponent_pending];
+        if plyr == TwoPlayers::TWO {
+            boards.swap(0, 1);
+            pending_placement.swap(0, 1);
+        }
+        Self {
+            to_move: obs.to_move,
+            fleet: obs.fleet.clone(),
+            rules: obs.rules,
+            pending_placement,
+            boards,
+            recent_shots: obs.recent_shots.clone(),
+            shots_left: obs.shots_left,
+        }
+    }
+}
 
-impl Ai<GameState, NUM_PLAYERS> for UnimplementedBattleshipAi {
-    fn new() -> Self {
-        Self {}
+impl PositionHash<NUM_PLAYERS> for GameState {
+    /// Zobrist hash: a key for every square that isn't open sea, every ship
+    /// left to place, the player to move and the shots they have left
+    fn position_hash(&self) -> u64 {
+        let mut features = Vec::new();
+        for (i, board) in self.boards.iter().enumerate() {
+            for (j, coord) in board.size().coords().enumerate() {
+                let square = match board.get_priv(coord) {
+                    Square::Sea => continue,
+                    Square::Ship => 0,
+                    Square::ShipHit => 1,
+                    Square::SeaMiss => 2,
+                    Square::ShipSunk => 3,
+                };
+                features.push(((i * 1000 + j) * 4 + square) as u64);
+            }
+        }
+        // The pending ships are sorted, so the same ships left to place
+        // always have the same keys
+        for (i, pending) in self.pending_placement.iter().enumerate() {
+            for (k, ship) in pending.iter().enumerate() {
+                let key = 10_000 + (i * 1000 + k) * 100 + ship.length();
+                features.push(key as u64);
+            }
+        }
+        features.push(1_000_000 + self.to_move.as_index() as u64);
+        features.push(2_000_000 + self.shots_left as u64);
+        features
+            .into_iter()
+            .fold(0, |hash, feature| hash ^ transposition::zobrist_key(feature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_game::Turn;
+    use crate::battleship::board::Square;
+    use crate::history::History;
+    use crate::random;
+
+    use std::collections::HashSet;
+
+    fn all_moves() -> Vec<Move> {
+        let mut result = Vec::new();
+        for len in 0..=6 {
+            for coord in BoardSize::default().coords() {
+                for dir in Dir::all() {
+                    let ship = ShipType::from_usize(len);
+                    result.push(Move::PlaceShip(ship, coord, dir));
+                }
+                result.push(Move::Shoot(coord));
+            }
+        }
+        result
+    }
+
+    fn assert_legal_moves_agree(game: &GameState, candidates: &[Move]) {
+        let legal = game.legal_moves();
+        for mv in &legal {
+            assert!(game.is_valid_move(mv));
+        }
+        for mv in candidates {
+            assert_eq!(legal.contains(mv), game.is_valid_move(mv));
+        }
+    }
+
+    #[test]
+    fn test_undo_placement() {
+        let mut history = History::new(GameState::game_setup(
+            BattleshipParams::default(),
+            &mut random::seeded(0),
+        ));
+        let mv = history.game().legal_moves()[0];
+        history.make_move(mv);
+        let ship = ShipType::from_usize(3);
+        assert!(!history.game().get_pending(TwoPlayers::ONE).contains(&ship));
+        assert_eq!(history.undo(), Some(Turn::Move(mv)));
+        assert!(history.game().get_pending(TwoPlayers::ONE).contains(&ship));
+        assert_eq!(
+            history.game().get_board(TwoPlayers::ONE).ship_squares_left(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_observe_hides_ships() {
+        let mut game = GameState::game_setup(
+            BattleshipParams::default(),
+            &mut random::seeded(0),
+        );
+        while !game.no_pending_placements() {
+            let mv = game.legal_moves()[0];
+            game.make_move(mv);
+        }
+        let obs = game.observe(TwoPlayers::ONE);
+        assert!(obs.pending().is_empty());
+        assert_eq!(obs.own_board().ship_squares_left(), 12);
+        assert_eq!(obs.opponent_board().ship_squares_left(), 12);
+        for coord in BoardSize::default().coords() {
+            let square = game.get_board(TwoPlayers::TWO).get_pub(coord);
+            assert_eq!(obs.opponent_board().get(coord), square);
+            assert_ne!(obs.opponent_board().get(coord), Square::Ship);
+        }
+        assert_eq!(
+            obs.opponent_board().disp(),
+            game.get_board(TwoPlayers::TWO).disp_pub()
+        );
     }
-    fn ai_move(&mut self, _game_state: &GameState, _plyr: TwoPlayers) -> Move {
-        unimplemented!()
+
+    #[test]
+    fn test_parse_move_round_trip() {
+        let game = GameState::game_setup(
+            BattleshipParams::default(),
+            &mut random::seeded(0),
+        );
+        for mv in all_moves() {
+            assert_eq!(game.parse_move(&mv.to_string()), Ok(mv));
+        }
+    }
+
+    fn coord(row: usize, col: usize) -> Coord {
+        Coord::from_usize(row, col).unwrap()
+    }
+
+    #[test]
+    fn test_notation() {
+        let game = GameState::game_setup(
+            BattleshipParams::default(),
+            &mut random::seeded(0),
+        );
+        let (down, right) =
+            (Dir::from_isize(1, 0).unwrap(), Dir::from_isize(0, 1).unwrap());
+        let carrier = ShipType::from_usize(5);
+        let examples = [
+            ("B7", Move::Shoot(coord(1, 6))),
+            ("j10", Move::Shoot(coord(9, 9))),
+            ("carrier A1 down", Move::PlaceShip(carrier, coord(0, 0), down)),
+            ("5 a1 Right", Move::PlaceShip(carrier, coord(0, 0), right)),
+            ("1 6", Move::Shoot(coord(1, 6))),
+            ("5 0 0 1 0", Move::PlaceShip(carrier, coord(0, 0), down)),
+        ];
+        for (raw, mv) in examples {
+            assert_eq!(game.parse_move(raw), Ok(mv));
+        }
+        assert_eq!(Move::Shoot(coord(1, 6)).to_string(), "B7");
+        let up_left = Dir::from_isize(-1, -1).unwrap();
+        let mv = Move::PlaceShip(carrier, coord(3, 4), up_left);
+        assert_eq!(mv.to_string(), "carrier D5 up-left");
+        let mv = Move::PlaceShip(ShipType::from_usize(7), coord(0, 0), down);
+        assert_eq!(mv.to_string(), "7 A1 down");
+        let errors = [
+            ("Z99", "isn't a square"),
+            ("B0", "isn't a square"),
+            ("boat A1 down", "isn't a ship"),
+            ("carrier A1 sideways", "isn't a direction"),
+            ("carrier A1", "Give a square"),
+        ];
+        for (raw, error) in errors {
+            assert!(
+                game.parse_move(raw).unwrap_err().contains(error),
+                "{}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn test_move_errors() {
+        let mut rng = random::seeded(0);
+        let game =
+            GameState::game_setup("preset small".parse().unwrap(), &mut rng);
+        let errors = [
+            ("B7", "Place your ships"),
+            ("carrier A1 down", "no carrier"),
+            ("cruiser F5 down", "doesn't fit"),
+        ];
+        for (raw, error) in errors {
+            let err = game.parse_valid_move(raw).unwrap_err();
+            assert!(err.contains(error), "{}: {}", raw, err);
+        }
+        assert!(game.query().contains("Place a ship"));
+        let game = placed_game("preset small", &mut rng);
+        let errors = [
+            ("G1", "off the board: the rows go from A to F"),
+            ("cruiser A1 down", "all placed"),
+        ];
+        for (raw, error) in errors {
+            let err = game.parse_valid_move(raw).unwrap_err();
+            assert!(err.contains(error), "{}: {}", raw, err);
+        }
+        assert!(game.query().contains("Fire"));
+        let state = game.print_state_visible(TwoPlayers::ONE);
+        assert!(state.contains("   1  2  3  4  5  6\nA"));
+    }
+
+    /// A game with both fleets placed at random
+    fn placed_game(params: &str, rng: &mut GameRng) -> GameState {
+        let mut game = GameState::game_setup(params.parse().unwrap(), rng);
+        while !game.no_pending_placements() {
+            let mv = *game.legal_moves().choose(rng).unwrap();
+            game.make_move(mv);
+        }
+        game
+    }
+
+    #[test]
+    fn test_shots_alternate() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game("", &mut rng);
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::ONE));
+        let target = BoardSize::default()
+            .coords()
+            .find(|&c| {
+                game.get_board(TwoPlayers::TWO).get_priv(c) == Square::Ship
+            })
+            .unwrap();
+        game.make_move(Move::Shoot(target));
+        assert_eq!(
+            game.get_board(TwoPlayers::TWO).get_priv(target),
+            Square::ShipHit
+        );
+        assert_eq!(game.get_board(TwoPlayers::ONE).ship_squares_left(), 12);
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::TWO));
+        let shot = Shot {
+            shooter: TwoPlayers::ONE,
+            coord: target,
+            result: HitResult::Hit,
+        };
+        assert_eq!(game.observe(TwoPlayers::TWO).recent_shots(), &[shot]);
+        assert!(game
+            .print_state_visible(TwoPlayers::ONE)
+            .contains(&format!("You fired at {}: hit", target)));
+        assert!(game
+            .print_state_visible(TwoPlayers::TWO)
+            .contains(&format!("Player 1 fired at {}: hit", target)));
+
+        let miss = BoardSize::default()
+            .coords()
+            .find(|&c| {
+                game.get_board(TwoPlayers::ONE).get_priv(c) == Square::Sea
+            })
+            .unwrap();
+        game.make_move(Move::Shoot(miss));
+        game.make_move(Move::Shoot(target));
+        let shots = game.observe(TwoPlayers::ONE).recent_shots().to_vec();
+        assert_eq!(shots.len(), 2);
+        assert_eq!(shots[0].result, HitResult::Miss);
+        assert_eq!(shots[1].result, HitResult::AlreadyShot);
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::TWO));
+    }
+
+    #[test]
+    fn test_sink_ship() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game("", &mut rng);
+        let target = game.get_board(TwoPlayers::TWO).ships()[0].clone();
+        let mut replies = BoardSize::default().coords();
+        for coord in target.squares() {
+            game.make_move(Move::Shoot(coord));
+            game.make_move(Move::Shoot(replies.next().unwrap()));
+        }
+        let obs = game.observe(TwoPlayers::ONE);
+        assert_eq!(
+            obs.recent_shots()[0].result,
+            HitResult::Sunk(target.ship())
+        );
+        let sunk = obs.opponent_board().sunk_ships();
+        assert_eq!(sunk.len(), 1);
+        assert_eq!(sunk[0].ship(), target.ship());
+        assert_eq!(sunk[0].squares(), target.squares());
+        for coord in target.squares() {
+            assert_eq!(obs.opponent_board().get(coord), Square::ShipSunk);
+        }
+        assert!(obs.opponent_board().disp().contains('#'));
+        let state = game.print_state_visible(TwoPlayers::ONE);
+        assert!(state.contains(&format!("and sunk the {}", target.ship())));
+        let afloat = ships_afloat(&game.fleet, obs.opponent_board());
+        assert_eq!(afloat.len(), 2);
+        assert!(!afloat.contains(&target.ship()));
+        for _ in 0..10 {
+            let guess = GameState::determinize(&obs, TwoPlayers::ONE, &mut rng);
+            let guessed = guess.get_board(TwoPlayers::TWO).public_view();
+            assert_eq!(guessed.disp(), obs.opponent_board().disp());
+            assert_eq!(guessed.sunk_ships(), obs.opponent_board().sunk_ships());
+        }
+    }
+
+    #[test]
+    fn test_first_shot() {
+        let mut rng = random::seeded(0);
+        let game = placed_game("first 2", &mut rng);
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::TWO));
+        for raw in &["", "first 2", "first random"] {
+            let params: BattleshipParams = raw.parse().unwrap();
+            assert_eq!(GameState::format_params(&params), *raw);
+        }
+        assert!(GameState::parse_params("first 3").is_err());
+        assert!(GameState::parse_params("second 1").is_err());
+        let firsts: HashSet<usize> = (0..20)
+            .map(|_| placed_game("first random", &mut rng).to_move.as_index())
+            .collect();
+        assert_eq!(firsts.len(), 2);
+    }
+
+    #[test]
+    fn test_board_and_fleet_params() {
+        let params = GameState::parse_params("preset classic").unwrap();
+        assert_eq!(params.size, BoardSize::default());
+        assert_eq!(params.fleet, ships_of_lengths(&[2, 3, 3, 4, 5]));
+        assert_eq!(
+            GameState::format_params(&params),
+            "fleet 5 4 3 3 2".to_string()
+        );
+        for raw in &["board 8x6; fleet 4 3 3", "first 2; board 4x4; fleet 3 2"]
+        {
+            let params: BattleshipParams = raw.parse().unwrap();
+            assert_eq!(GameState::format_params(&params), *raw);
+        }
+        assert_eq!(
+            parse_layout("tiny"),
+            Ok((BoardSize::new(4, 4).unwrap(), ships_of_lengths(&[2, 3])))
+        );
+        assert_eq!(
+            parse_layout("5x7 2 2"),
+            Ok((BoardSize::new(5, 7).unwrap(), ships_of_lengths(&[2, 2])))
+        );
+        for raw in &[
+            "preset huge",
+            "board 0x5",
+            "board 27x10",
+            "board 10",
+            "fleet",
+            "fleet 3 x",
+            "board 4x4; fleet 5",
+            "board 4x4; fleet 3 3 3",
+        ] {
+            assert!(GameState::parse_params(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_classic_fleet() {
+        let mut rng = random::seeded(0);
+        let mut game = GameState::game_setup(
+            GameState::parse_params("preset classic").unwrap(),
+            &mut rng,
+        );
+        let cruiser = ShipType::from_usize(3);
+        let mv = *game
+            .legal_moves()
+            .iter()
+            .find(|mv| matches!(mv, Move::PlaceShip(ship, _, _) if *ship == cruiser))
+            .unwrap();
+        game.make_move(mv);
+        // The second cruiser is still to be placed
+        assert!(game.get_pending(TwoPlayers::ONE).contains(&cruiser));
+        assert_eq!(game.get_pending(TwoPlayers::ONE).len(), 4);
+        // Each placement is listed once, though there are two cruisers
+        let legal = game.legal_moves();
+        for (i, mv) in legal.iter().enumerate() {
+            assert!(!legal[..i].contains(mv));
+        }
+        let game = placed_game("preset classic", &mut rng);
+        assert_eq!(game.get_board(TwoPlayers::ONE).ship_squares_left(), 17);
+        assert_eq!(game.get_board(TwoPlayers::TWO).ships().len(), 5);
+    }
+
+    #[test]
+    fn test_ships_to_the_edge() {
+        let mut rng = random::seeded(0);
+        let mut game = GameState::game_setup(
+            "board 5x5; fleet 5 2".parse().unwrap(),
+            &mut rng,
+        );
+        // A ship as long as the board, from edge to edge
+        for raw in &["carrier A1 right", "carrier E5 up", "carrier E1 up-right"]
+        {
+            let mv = raw.parse().unwrap();
+            assert!(game.is_valid_move(&mv), "{}", raw);
+            assert!(game.legal_moves().contains(&mv), "{}", raw);
+        }
+        game.make_move("carrier C1 right".parse().unwrap());
+        // A short ship ending on the last row, and one on the first column
+        for raw in &["2 D5 down", "2 A2 left"] {
+            let mv = raw.parse().unwrap();
+            assert!(game.is_valid_move(&mv), "{}", raw);
+        }
+        assert!(!game.is_valid_move(&"2 E5 down".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_small_board() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game("preset tiny", &mut rng);
+        assert_eq!(game.legal_moves().len(), 16);
+        assert!(game.is_valid_move(&Move::Shoot(coord(3, 3))));
+        assert!(!game.is_valid_move(&Move::Shoot(coord(4, 0))));
+        while game.cur_player().is_some() {
+            let mv = *game.legal_moves().choose(&mut rng).unwrap();
+            game.make_move(mv);
+        }
+        let obs = game.observe(TwoPlayers::ONE);
+        let guess = GameState::determinize(&obs, TwoPlayers::ONE, &mut rng);
+        assert_eq!(guess.size(), BoardSize::new(4, 4).unwrap());
+    }
+
+    /// A square on a player's board that hasn't been shot, with or
+    /// without a ship
+    fn unshot(game: &GameState, plyr: TwoPlayers, ship: bool) -> Coord {
+        let square = if ship { Square::Ship } else { Square::Sea };
+        let board = game.get_board(plyr);
+        board.size().coords().find(|&c| board.get_priv(c) == square).unwrap()
+    }
+
+    #[test]
+    fn test_salvo() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game("rules salvo", &mut rng);
+        let (one, two) = (TwoPlayers::ONE, TwoPlayers::TWO);
+        assert!(game.print_state_visible(one).contains("3 shots left"));
+        for _ in 0..3 {
+            assert_eq!(game.status(), GameStatus::ToMove(one));
+            game.make_move(Move::Shoot(unshot(&game, two, false)));
+        }
+        assert_eq!(game.observe(two).recent_shots().len(), 3);
+        // Sink player 1's shortest ship during player 2's salvo
+        let ships = game.get_board(one).ships();
+        let target = ships.iter().find(|s| s.ship().length() == 3).unwrap();
+        let target = target.clone();
+        for coord in target.squares() {
+            assert_eq!(game.status(), GameStatus::ToMove(two));
+            game.make_move(Move::Shoot(coord));
+        }
+        assert_eq!(game.status(), GameStatus::ToMove(one));
+        assert_eq!(game.observe(one).shots_left(), 2);
+        // Player 2's shots replaced player 1's, and stay until player 2
+        // fires again
+        game.make_move(Move::Shoot(unshot(&game, two, false)));
+        let shots = game.observe(one).recent_shots().to_vec();
+        assert_eq!(shots.len(), 4);
+        assert_eq!(shots[2].result, HitResult::Sunk(target.ship()));
+    }
+
+    #[test]
+    fn test_extra_shot() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game("rules extra-shot", &mut rng);
+        let (one, two) = (TwoPlayers::ONE, TwoPlayers::TWO);
+        game.make_move(Move::Shoot(unshot(&game, two, true)));
+        assert_eq!(game.status(), GameStatus::ToMove(one));
+        game.make_move(Move::Shoot(unshot(&game, two, false)));
+        assert_eq!(game.status(), GameStatus::ToMove(two));
+    }
+
+    #[test]
+    fn test_placement_rules() {
+        let mut rng = random::seeded(0);
+        let params = "rules orthogonal no-touching";
+        let mut game = GameState::game_setup(params.parse().unwrap(), &mut rng);
+        let (right, down_right) =
+            (Dir::from_isize(0, 1).unwrap(), Dir::from_isize(1, 1).unwrap());
+        let ship = ShipType::from_usize(3);
+        let diagonal = Move::PlaceShip(ship, coord(0, 0), down_right);
+        assert!(game.check_move(&diagonal).unwrap_err().contains("row"));
+        game.make_move(Move::PlaceShip(ship, coord(0, 0), right));
+        let ship = ShipType::from_usize(4);
+        let touching = Move::PlaceShip(ship, coord(1, 3), right);
+        assert!(game.check_move(&touching).unwrap_err().contains("touch"));
+        assert!(game.is_valid_move(&Move::PlaceShip(ship, coord(2, 3), right)));
+        assert_legal_moves_agree(&game, &all_moves());
+        for mv in game.legal_moves() {
+            assert!(
+                matches!(mv, Move::PlaceShip(_, _, dir) if dir.is_orthogonal())
+            );
+        }
+        assert!(
+            GameState::parse_params("preset tiny; rules no-touching").is_err()
+        );
+        assert!(GameState::parse_params("rules sideways").is_err());
+        let params: BattleshipParams = params.parse().unwrap();
+        assert_eq!(
+            GameState::format_params(&params),
+            "rules orthogonal no-touching"
+        );
+    }
+
+    #[test]
+    fn test_room_for_the_fleet() {
+        let mut rng = random::seeded(0);
+        let params = "board 5x5; fleet 3 3; rules no-touching";
+        let game = GameState::game_setup(params.parse().unwrap(), &mut rng);
+        // A cruiser across the middle fits, but leaves no room for the other
+        let mv = "cruiser B2 down-right".parse().unwrap();
+        assert!(game.check_move(&mv).unwrap_err().contains("no room"));
+        assert!(!game.legal_moves().contains(&mv));
+        assert!(game.is_valid_move(&"cruiser A1 right".parse().unwrap()));
+        assert_legal_moves_agree(&game, &all_moves());
+        // Ships placed anywhere offered always leave room for the rest
+        for _ in 0..20 {
+            let mut game = GameState::game_setup(
+                "board 6x6; fleet 4 3 2; rules no-touching".parse().unwrap(),
+                &mut rng,
+            );
+            while game.cur_player() == Some(TwoPlayers::ONE) {
+                let mv = *game.legal_moves().choose(&mut rng).unwrap();
+                game.make_move(mv);
+            }
+        }
+    }
+
+    #[test]
+    fn test_determinize_rules() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game(
+            "preset classic; rules orthogonal no-touching",
+            &mut rng,
+        );
+        let target = game.get_board(TwoPlayers::TWO).ships()[0].clone();
+        for coord in target.squares() {
+            game.get_board_mut(TwoPlayers::TWO).shoot(coord);
+        }
+        let obs = game.observe(TwoPlayers::ONE);
+        let view = obs.opponent_board();
+        for _ in 0..10 {
+            let guess = GameState::determinize(&obs, TwoPlayers::ONE, &mut rng);
+            let board = guess.get_board(TwoPlayers::TWO);
+            assert_eq!(board.public_view().disp(), view.disp());
+            for ship in board.ships() {
+                assert!(ship.dir().is_orthogonal());
+                let squares = ship.squares();
+                for coord in board.size().neighbors(&squares) {
+                    assert_ne!(board.get_priv(coord), Square::Ship);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_board_diagonal_hit() {
+        // A cruiser from E7 through D8, with misses all around E7, so
+        // whichever ship was hit at E7 must lie through D8
+        let params: BattleshipParams = "preset classic".parse().unwrap();
+        let mut board = Board::new(params.size);
+        let (right, down, up_right) = (
+            Dir::from_isize(0, 1).unwrap(),
+            Dir::from_isize(1, 0).unwrap(),
+            Dir::from_isize(-1, 1).unwrap(),
+        );
+        let placements = [
+            (5, coord(0, 0), right),
+            (4, coord(9, 0), right),
+            (3, coord(4, 6), up_right),
+            (3, coord(6, 0), down),
+            (2, coord(7, 8), right),
+        ];
+        for (length, origin, dir) in placements {
+            let ship = ShipType::from_usize(length);
+            assert!(board.place_ship(ship, origin, dir));
+        }
+        board.shoot(coord(4, 6));
+        board.shoot(coord(3, 7));
+        for square in board.size().neighbors(&[coord(4, 6)]) {
+            board.shoot(square);
+        }
+        let view = board.public_view();
+        let mut rng = random::seeded(0);
+        for _ in 0..20 {
+            let guess =
+                sample_board(&view, &params.fleet, &params.rules, &mut rng)
+                    .unwrap();
+            assert_eq!(guess.public_view().disp(), view.disp());
+            let ship = guess
+                .ships()
+                .iter()
+                .find(|ship| ship.squares().contains(&coord(4, 6)))
+                .unwrap()
+                .clone();
+            assert!(ship.squares().contains(&coord(3, 7)));
+        }
+    }
+
+    #[test]
+    fn test_determinize() {
+        let mut rng = random::seeded(0);
+        let mut game = placed_game("", &mut rng);
+        for coord in BoardSize::default().coords().step_by(3) {
+            game.get_board_mut(TwoPlayers::TWO).shoot(coord);
+        }
+        let obs = game.observe(TwoPlayers::ONE);
+        for _ in 0..10 {
+            let guess = GameState::determinize(&obs, TwoPlayers::ONE, &mut rng);
+            let (actual, guessed) = (
+                game.get_board(TwoPlayers::TWO),
+                guess.get_board(TwoPlayers::TWO),
+            );
+            assert!(guessed.eq_pub(actual));
+            assert_eq!(guessed.disp_pub(), actual.disp_pub());
+            assert_eq!(guessed.ship_squares_left(), actual.ship_squares_left());
+            assert!(guess
+                .get_board(TwoPlayers::ONE)
+                .eq_priv(game.get_board(TwoPlayers::ONE)));
+            assert_eq!(guess.status(), game.status());
+        }
+    }
+
+    #[test]
+    fn test_determinize_placement() {
+        let mut rng = random::seeded(0);
+        let mut game =
+            GameState::game_setup(BattleshipParams::default(), &mut rng);
+        let mv = game.legal_moves()[0];
+        game.make_move(mv);
+        let obs = game.observe(TwoPlayers::ONE);
+        let guess = GameState::determinize(&obs, TwoPlayers::ONE, &mut rng);
+        assert_eq!(guess.legal_moves(), game.legal_moves());
+        assert_eq!(guess.get_pending(TwoPlayers::TWO).len(), 3);
+    }
+
+    #[test]
+    fn test_determinize_first_shot() {
+        let mut rng = random::seeded(0);
+        let mut game =
+            GameState::game_setup("first 2".parse().unwrap(), &mut rng);
+        let mv = game.legal_moves()[0];
+        game.make_move(mv);
+        let obs = game.observe(TwoPlayers::ONE);
+        let mut guess = GameState::determinize(&obs, TwoPlayers::ONE, &mut rng);
+        while !guess.no_pending_placements() {
+            let mv = *guess.legal_moves().choose(&mut rng).unwrap();
+            guess.make_move(mv);
+        }
+        assert_eq!(guess.status(), GameStatus::ToMove(TwoPlayers::TWO));
+    }
+
+    #[test]
+    fn test_shots_commute() {
+        let mut rng = random::seeded(0);
+        let game = placed_game("", &mut rng);
+        let shots: Vec<Coord> =
+            BoardSize::default().coords().step_by(7).collect();
+        let mut forward = game.clone();
+        let mut backward = game.clone();
+        for (&a, &b) in shots.iter().zip(shots.iter().rev()) {
+            forward.get_board_mut(TwoPlayers::TWO).shoot(a);
+            backward.get_board_mut(TwoPlayers::TWO).shoot(b);
+        }
+        assert_eq!(forward.position_hash(), backward.position_hash());
+        assert_ne!(forward.position_hash(), game.position_hash());
+    }
+
+    #[test]
+    fn test_legal_moves_agree() {
+        let mut game = GameState::game_setup(
+            BattleshipParams::default(),
+            &mut random::seeded(0),
+        );
+        let candidates = all_moves();
+        while !game.no_pending_placements() {
+            assert_legal_moves_agree(&game, &candidates);
+            let mv = game.legal_moves()[0];
+            game.make_move(mv);
+        }
+        assert_legal_moves_agree(&game, &candidates);
+        assert_eq!(game.legal_moves().len(), 100);
     }
 }
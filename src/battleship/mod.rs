@@ -1,2 +1,4 @@
+pub mod ai;
 pub mod board;
 pub mod game;
+pub mod rules;
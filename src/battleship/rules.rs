@@ -0,0 +1,215 @@
+/*
+    Rule variants
+
+    - orthogonal: ships must lie along a row or column (otherwise they may
+      also lie diagonally)
+    - no-touching: ships can't be placed next to each other, even at a
+      corner
+    - salvo: each turn, a player fires one shot for every ship they have
+      afloat
+    - extra-shot: a player who hits a ship fires again
+
+    The shots of a turn are fired one at a time, and each is reported as
+    soon as it is fired.
+*/
+
+use super::board::{Board, Coord, Dir, PublicBoard, ShipType, Square};
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Rules {
+    pub orthogonal: bool,
+    pub no_touching: bool,
+    pub salvo: bool,
+    pub extra_shot: bool,
+}
+
+const RULE_NAMES: [&str; 4] =
+    ["orthogonal", "no-touching", "salvo", "extra-shot"];
+
+impl Rules {
+    fn flags(&self) -> [bool; 4] {
+        [self.orthogonal, self.no_touching, self.salvo, self.extra_shot]
+    }
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "orthogonal" => Some(&mut self.orthogonal),
+            "no-touching" => Some(&mut self.no_touching),
+            "salvo" => Some(&mut self.salvo),
+            "extra-shot" => Some(&mut self.extra_shot),
+            _ => None,
+        }
+    }
+
+    /// Directions a ship may lie in
+    pub fn dirs(self) -> impl Iterator<Item = Dir> {
+        Dir::all().filter(move |dir| !self.orthogonal || dir.is_orthogonal())
+    }
+
+    /// Whether a ship may be placed on a board, from coord in direction dir
+    pub fn allows_placement(
+        &self,
+        board: &Board,
+        ship: ShipType,
+        coord: Coord,
+        dir: Dir,
+    ) -> bool {
+        (!self.orthogonal || dir.is_orthogonal())
+            && board.valid_ship_line(coord, dir, ship.length())
+            && !(self.no_touching && touches_ship(board, ship, coord, dir))
+    }
+
+    /// Why a ship may not be placed on a board (apart from not fitting)
+    pub fn check_placement(
+        &self,
+        board: &Board,
+        ship: ShipType,
+        coord: Coord,
+        dir: Dir,
+    ) -> Result<(), String> {
+        if self.orthogonal && !dir.is_orthogonal() {
+            return Err("Ships must lie along a row or column. ".to_string());
+        }
+        if !board.valid_ship_line(coord, dir, ship.length()) {
+            return Err(format!(
+                "The {} doesn't fit there: it would go off the board or \
+                cross another ship. ",
+                ship
+            ));
+        }
+        if self.no_touching && touches_ship(board, ship, coord, dir) {
+            return Err(format!("The {} would touch another ship. ", ship));
+        }
+        debug_assert!(self.allows_placement(board, ship, coord, dir));
+        Ok(())
+    }
+
+    /// Whether a ship that is still afloat could lie on the given squares
+    /// (in direction dir), judging by the ships that have been hit next to
+    /// them. Shots at the squares themselves aren't checked.
+    pub fn allows_hidden_placement(
+        &self,
+        view: &PublicBoard,
+        dir: Dir,
+        squares: &[Coord],
+    ) -> bool {
+        if self.orthogonal && !dir.is_orthogonal() {
+            return false;
+        }
+        let size = view.size();
+        !self.no_touching
+            || squares.iter().all(|&square| {
+                Dir::all()
+                    .filter_map(|next| size.step(square, next))
+                    .filter(|c| !squares.contains(c))
+                    .all(|c| {
+                        !matches!(
+                            view.get(c),
+                            Square::ShipHit | Square::ShipSunk
+                        )
+                    })
+            })
+    }
+
+    /// Number of shots a player with ships_afloat ships fires in a turn
+    /// (not counting extra shots)
+    pub fn shots_per_turn(&self, ships_afloat: usize) -> usize {
+        if self.salvo {
+            ships_afloat.max(1)
+        } else {
+            1
+        }
+    }
+}
+
+/// Whether a ship placed on a board (where it fits) would be next to
+/// another ship, even at a corner
+fn touches_ship(board: &Board, ship: ShipType, coord: Coord, dir: Dir) -> bool {
+    let size = board.size();
+    let squares = size.line(coord, dir, ship.length()).unwrap();
+    // The ship's own squares are open sea, so they needn't be left out
+    squares.iter().any(|&square| {
+        Dir::all()
+            .filter_map(|next| size.step(square, next))
+            .any(|c| board.get_priv(c) != Square::Sea)
+    })
+}
+
+/// The rules in play, e.g. "orthogonal salvo" (empty for the standard
+/// rules)
+impl Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = RULE_NAMES
+            .iter()
+            .zip(self.flags())
+            .filter(|(_, on)| *on)
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl FromStr for Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut rules = Self::default();
+        for name in s.split_whitespace() {
+            let flag = rules.flag_mut(name).ok_or_else(|| {
+                format!(
+                    "Unknown rule: {} (the rules are {}). ",
+                    name,
+                    RULE_NAMES.join(", ")
+                )
+            })?;
+            *flag = true;
+        }
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(row: usize, col: usize) -> Coord {
+        Coord::from_usize(row, col).unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        for raw in &["", "orthogonal", "no-touching salvo extra-shot"] {
+            assert_eq!(raw.parse::<Rules>().unwrap().to_string(), *raw);
+        }
+        assert!("salvo diagonal".parse::<Rules>().is_err());
+    }
+
+    #[test]
+    fn test_placement() {
+        let mut board = Board::default();
+        let (right, down_right) =
+            (Dir::from_isize(0, 1).unwrap(), Dir::from_isize(1, 1).unwrap());
+        let ship = ShipType::from_usize(3);
+        assert!(board.place_ship(ship, coord(2, 2), right));
+        let rules: Rules = "orthogonal no-touching".parse().unwrap();
+        assert!(Rules::default().allows_placement(
+            &board,
+            ship,
+            coord(3, 2),
+            down_right
+        ));
+        assert!(!rules.allows_placement(&board, ship, coord(5, 2), down_right));
+        // Touching at a corner
+        assert!(Rules::default().allows_placement(
+            &board,
+            ship,
+            coord(3, 5),
+            right
+        ));
+        assert!(!rules.allows_placement(&board, ship, coord(3, 5), right));
+        assert!(rules.allows_placement(&board, ship, coord(4, 5), right));
+        assert_eq!(rules.dirs().count(), 4);
+    }
+}
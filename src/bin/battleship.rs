@@ -1,7 +1,14 @@
-use games::battleship::game::{GameState, UnimplementedBattleshipAi};
+use games::battleship::ai::DensityAi;
+use games::battleship::game::GameState;
+use games::console::Terminal;
 use games::play;
 
 fn main() {
     println!("======= BATTLESHIP =======");
-    play::play_vs_ai_choose_player::<GameState, UnimplementedBattleshipAi, 2>();
+    let mut console = Terminal;
+    let seed = play::game_seed(&mut console);
+    play::play_vs_ai_choose_player::<GameState, DensityAi, 2>(
+        &mut console,
+        seed,
+    );
 }
@@ -0,0 +1,123 @@
+/*
+    Benchmark for the Battleship AIs: the average number of shots each one
+    needs to sink a randomly placed fleet
+
+    Usage: battleship_benchmark [number of games] [seed] [setup]
+    where setup is in the format of the game's setup parameters, e.g.
+    "preset classic"
+*/
+
+use games::battleship::ai;
+use games::battleship::board::{Board, Coord, Dir, PublicBoard, Square};
+use games::battleship::game::{self, BattleshipParams};
+use games::random::{self, GameRng};
+
+use rand::seq::SliceRandom;
+
+/// A way of choosing where to shoot, knowing the fleet and rules
+type Strategy = fn(&PublicBoard, &BattleshipParams, &mut GameRng) -> Coord;
+
+/// A board with the fleet placed at random, each ship uniformly among the
+/// places that leave room for the rest
+fn random_fleet(params: &BattleshipParams, rng: &mut GameRng) -> Board {
+    let mut board = Board::new(params.size);
+    for (i, &ship) in params.fleet.iter().enumerate().rev() {
+        let rest = &params.fleet[..i];
+        let placements: Vec<(Coord, Dir)> = params
+            .size
+            .coords()
+            .flat_map(|coord| params.rules.dirs().map(move |dir| (coord, dir)))
+            .filter(|&(coord, dir)| {
+                if !params.rules.allows_placement(&board, ship, coord, dir) {
+                    return false;
+                }
+                let mut next = board.clone();
+                assert!(next.place_ship(ship, coord, dir));
+                game::can_place_all(&next, rest, &params.rules)
+            })
+            .collect();
+        let &(coord, dir) = placements.choose(rng).unwrap();
+        assert!(board.place_ship(ship, coord, dir));
+    }
+    board
+}
+
+/// Number of shots a strategy takes to sink every ship on the board
+fn shots_to_win(
+    mut board: Board,
+    params: &BattleshipParams,
+    choose_shot: Strategy,
+    rng: &mut GameRng,
+) -> usize {
+    let mut shots = 0;
+    while board.ship_squares_left() > 0 {
+        board.shoot(choose_shot(&board.public_view(), params, rng));
+        shots += 1;
+    }
+    shots
+}
+
+fn random_shot(
+    board: &PublicBoard,
+    _params: &BattleshipParams,
+    rng: &mut GameRng,
+) -> Coord {
+    let unshot: Vec<Coord> = board
+        .size()
+        .coords()
+        .filter(|&c| board.get(c) == Square::Sea)
+        .collect();
+    *unshot.choose(rng).unwrap()
+}
+
+fn hunt_target_shot(
+    board: &PublicBoard,
+    params: &BattleshipParams,
+    rng: &mut GameRng,
+) -> Coord {
+    ai::choose_shot(board, &params.rules, rng)
+}
+
+fn densest_shot(
+    board: &PublicBoard,
+    params: &BattleshipParams,
+    rng: &mut GameRng,
+) -> Coord {
+    let ships = game::ships_afloat(&params.fleet, board);
+    ai::choose_densest_shot(board, &ships, &params.rules, rng)
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let games: usize = args
+        .next()
+        .map(|arg| arg.parse().expect("number of games should be an integer"))
+        .unwrap_or(100);
+    let seed = args
+        .next()
+        .map(|arg| arg.parse().expect("seed should be an integer"))
+        .unwrap_or(0);
+    let params: BattleshipParams = match args.next() {
+        Some(arg) => arg.parse().unwrap_or_else(|err| panic!("{}", err)),
+        None => BattleshipParams::default(),
+    };
+    let mut rng = random::seeded(seed);
+
+    let strategies: [(&str, Strategy); 3] = [
+        ("Random", random_shot),
+        ("Hunt and target", hunt_target_shot),
+        ("Probability density", densest_shot),
+    ];
+    let boards: Vec<Board> =
+        (0..games).map(|_| random_fleet(&params, &mut rng)).collect();
+    println!("Average shots to sink the fleet, over {} games:", games);
+    for (name, strategy) in &strategies {
+        let total: usize = boards
+            .iter()
+            .map(|board| {
+                shots_to_win(board.clone(), &params, *strategy, &mut rng)
+            })
+            .sum();
+        println!("{:>20}: {:.1}", name, total as f64 / games as f64);
+    }
+}
@@ -0,0 +1,14 @@
+use games::console::Terminal;
+use games::impartial::GrundyAi;
+use games::kayles::KaylesState;
+use games::play;
+
+fn main() {
+    println!("======= KAYLES =======");
+    let mut console = Terminal;
+    let seed = play::game_seed(&mut console);
+    play::play_vs_ai_or_yourself::<KaylesState, GrundyAi<KaylesState, 2>, 2>(
+        &mut console,
+        seed,
+    );
+}
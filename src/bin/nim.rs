@@ -1,7 +1,10 @@
-use games::nim::NimState;
+use games::console::Terminal;
+use games::nim::{NimAi, NimState};
 use games::play;
 
 fn main() {
     println!("======= NIM =======");
-    play::play_vs_yourself::<NimState<2>, 2>();
+    let mut console = Terminal;
+    let seed = play::game_seed(&mut console);
+    play::play_vs_ai_or_yourself::<NimState<2>, NimAi, 2>(&mut console, seed);
 }
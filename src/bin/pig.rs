@@ -0,0 +1,14 @@
+use games::ai::MctsAi;
+use games::console::Terminal;
+use games::pig::PigState;
+use games::play;
+
+fn main() {
+    println!("======= PIG =======");
+    let mut console = Terminal;
+    let seed = play::game_seed(&mut console);
+    play::play_vs_ai_or_yourself::<PigState<2>, MctsAi<PigState<2>, 2>, 2>(
+        &mut console,
+        seed,
+    );
+}
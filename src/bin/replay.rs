@@ -0,0 +1,50 @@
+use games::abstract_game::AbstractGame;
+use games::battleship::game::GameState;
+use games::console::Terminal;
+use games::kayles::KaylesState;
+use games::nim::NimState;
+use games::pig::PigState;
+use games::play;
+use games::record::{self, GameRecord};
+use games::rps::RpsState;
+
+use std::fmt::Display;
+use std::fs;
+
+/// Parse a record of a game of type G and replay it
+fn replay<G, const N: usize>(raw: &str) -> Result<(), String>
+where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
+{
+    play::replay(&mut Terminal, &GameRecord::<G, N>::from_text(raw)?);
+    Ok(())
+}
+
+fn run(path: &str) -> Result<(), String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|err| format!("Could not load {}: {}. ", path, err))?;
+    match record::game_name(&raw)? {
+        name if name == NimState::<2>::NAME => replay::<NimState<2>, 2>(&raw),
+        name if name == PigState::<2>::NAME => replay::<PigState<2>, 2>(&raw),
+        name if name == RpsState::NAME => replay::<RpsState, 2>(&raw),
+        name if name == KaylesState::NAME => replay::<KaylesState, 2>(&raw),
+        name if name == GameState::NAME => replay::<GameState, 2>(&raw),
+        name => Err(format!("Unknown game: {}. ", name)),
+    }
+}
+
+fn main() {
+    println!("======= REPLAY =======");
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            println!("Usage: replay <record file>");
+            return;
+        }
+    };
+    if let Err(msg) = run(&path) {
+        println!("{}", msg);
+    }
+}
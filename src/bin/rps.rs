@@ -0,0 +1,14 @@
+use games::ai::RandomAi;
+use games::console::Terminal;
+use games::play;
+use games::rps::RpsState;
+
+fn main() {
+    println!("======= ROCK PAPER SCISSORS =======");
+    let mut console = Terminal;
+    let seed = play::game_seed(&mut console);
+    play::play_vs_ai_or_yourself::<RpsState, RandomAi<RpsState, 2>, 2>(
+        &mut console,
+        seed,
+    );
+}
@@ -0,0 +1,104 @@
+/*
+    Input and output for playing games
+
+    Everything the play loops and game setup read or print goes through the
+    Console trait, so that games can be played at a terminal or driven by a
+    script (for example in tests).
+*/
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+pub trait Console {
+    /// Read a line of input (without the trailing newline)
+    fn read_line(&mut self) -> String;
+
+    /// Show some text to the user
+    fn print(&mut self, text: &str);
+
+    /// Show a line of text to the user
+    fn println(&mut self, text: &str) {
+        self.print(text);
+        self.print("\n");
+    }
+}
+
+/// Console reading from stdin and printing to stdout
+pub struct Terminal;
+
+impl Console for Terminal {
+    fn read_line(&mut self) -> String {
+        io::stdout().flush().expect("failed to flush stdout");
+        io::stdin()
+            .lock()
+            .lines()
+            .next()
+            .expect("failed to get line from stdin")
+            .expect("failed to get line from stdin")
+    }
+
+    fn print(&mut self, text: &str) {
+        print!("{}", text);
+    }
+}
+
+/// Console that reads from a fixed list of input lines and collects
+/// everything printed. Panics if it runs out of input.
+pub struct Scripted {
+    input: VecDeque<String>,
+    output: String,
+}
+
+impl Scripted {
+    pub fn new(input: &[&str]) -> Self {
+        let input = input.iter().map(|line| line.to_string()).collect();
+        Self { input, output: String::new() }
+    }
+
+    /// Everything printed so far
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Whether every input line has been read
+    pub fn is_finished(&self) -> bool {
+        self.input.is_empty()
+    }
+}
+
+impl Console for Scripted {
+    fn read_line(&mut self) -> String {
+        let line = self.input.pop_front().expect("scripted input ran out");
+        // Echo the input, as it would appear at a terminal
+        self.println(&line);
+        line
+    }
+
+    fn print(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util;
+
+    #[test]
+    fn test_scripted() {
+        let mut console = Scripted::new(&["x", "-1", "5"]);
+        let n: usize = util::from_user_input_satisfying(
+            &mut console,
+            "Number? ",
+            "Not a number. ",
+            "Too small. ",
+            |&n| n > 2,
+        );
+        assert_eq!(n, 5);
+        assert!(console.is_finished());
+        assert_eq!(
+            console.output(),
+            "Number? x\nNot a number. -1\nNot a number. 5\n"
+        );
+    }
+}
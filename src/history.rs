@@ -0,0 +1,170 @@
+/*
+    History of the moves made in a game, with support for undo and redo.
+
+    Rather than asking every game for an unmake_move, the history keeps a
+    snapshot of the game before each move, so any game that can be cloned
+    can be taken back.
+*/
+
+use super::abstract_game::{AbstractGame, Turn};
+
+pub struct History<G, const N: usize>
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone,
+{
+    game: G,
+    // For each turn made: the game before the turn, and the turn
+    past: Vec<(G, Turn<G::Move, N>)>,
+    // Turns that were undone, most recently undone last
+    future: Vec<Turn<G::Move, N>>,
+}
+
+impl<G, const N: usize> History<G, N>
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone,
+{
+    /// Start a history from a starting position
+    pub fn new(game: G) -> Self {
+        Self { game, past: Vec::new(), future: Vec::new() }
+    }
+
+    /// Current state of the game
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// Turns made so far, in order
+    pub fn turns(&self) -> impl Iterator<Item = &Turn<G::Move, N>> {
+        self.past.iter().map(|(_, turn)| turn)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.past.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.future.is_empty()
+    }
+
+    /// Make a (valid) move (see make_turn)
+    pub fn make_move(&mut self, mv: G::Move) {
+        debug_assert!(self.game.is_valid_move(&mv));
+        self.make_turn(Turn::Move(mv));
+    }
+
+    /// Take back the last turn, returning it (or None if there are no
+    /// turns to take back)
+    pub fn undo(&mut self) -> Option<Turn<G::Move, N>> {
+        let (game, turn) = self.past.pop()?;
+        self.game = game;
+        self.future.push(turn.clone());
+        Some(turn)
+    }
+
+    /// Replay the last turn that was undone, returning it (or None if
+    /// there are no turns to replay)
+    pub fn redo(&mut self) -> Option<Turn<G::Move, N>> {
+        let turn = self.future.pop()?;
+        self.push_turn(turn.clone());
+        Some(turn)
+    }
+
+    /// Make a (valid) turn. This discards any turns that could be redone,
+    /// unless it is the next of them: then the rest are kept, so that
+    /// chance turns after it can be replayed rather than drawn again.
+    pub fn make_turn(&mut self, turn: Turn<G::Move, N>) {
+        if self.future.last() == Some(&turn) {
+            self.future.pop();
+        } else {
+            self.future.clear();
+        }
+        self.push_turn(turn);
+    }
+
+    fn push_turn(&mut self, turn: Turn<G::Move, N>) {
+        self.past.push((self.game.clone(), turn.clone()));
+        self.game.make_turn(turn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nim::{NimParams, NimState};
+    use crate::player::TwoPlayers;
+    use crate::random;
+
+    fn play_first_moves(history: &mut History<NimState<2>, 2>, n: usize) {
+        for _ in 0..n {
+            let mv = history.game().legal_moves()[0].clone();
+            history.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let start = NimState::<2>::game_setup(
+            NimParams::new(vec![2, 3]),
+            &mut random::seeded(0),
+        );
+        let mut history = History::new(start);
+        assert!(!history.can_undo());
+        assert!(history.undo().is_none());
+
+        play_first_moves(&mut history, 2);
+        let after_two = history.game().print_state_visible(TwoPlayers::ONE);
+        assert_eq!(history.turns().count(), 2);
+
+        let undone = history.undo().unwrap();
+        assert_eq!(history.turns().count(), 1);
+        assert!(history.can_redo());
+        assert_eq!(history.redo(), Some(undone));
+        assert!(!history.can_redo());
+        assert_eq!(
+            history.game().print_state_visible(TwoPlayers::ONE),
+            after_two
+        );
+
+        history.undo();
+        history.undo();
+        assert!(!history.can_undo());
+        assert_eq!(
+            history.game().print_state_visible(TwoPlayers::ONE),
+            "Piles: [2, 3]"
+        );
+    }
+
+    #[test]
+    fn test_move_clears_redo() {
+        let mut history = History::new(NimState::<2>::game_setup(
+            NimParams::new(vec![4]),
+            &mut random::seeded(0),
+        ));
+        play_first_moves(&mut history, 1);
+        history.undo();
+        assert!(history.can_redo());
+        play_first_moves(&mut history, 1);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_same_move_keeps_redo() {
+        let mut history = History::new(NimState::<2>::game_setup(
+            NimParams::new(vec![4]),
+            &mut random::seeded(0),
+        ));
+        play_first_moves(&mut history, 2);
+        history.undo();
+        history.undo();
+        // Making the move that was undone first keeps the one after it
+        play_first_moves(&mut history, 1);
+        assert_eq!(
+            history.redo().map(|turn| turn.to_string()).as_deref(),
+            Some("Take 1 from pile 1")
+        );
+        history.undo();
+        history.make_move(history.game().legal_moves()[1].clone());
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,220 @@
+/*
+    Impartial games and the Sprague–Grundy theory
+
+    In an impartial game both players have the same moves from every
+    position, and (in normal play) the player who cannot move loses. Many
+    such games break up into independent components (the piles in Nim, the
+    rows of pins in Kayles), and the whole game is the disjunctive sum of
+    its components: each move is made in exactly one component.
+
+    Every component has a Grundy value (the least value not among the
+    values of its options), and a sum of components is lost for the player
+    to move exactly when the XOR of their Grundy values is zero. This gives
+    a perfect AI for any impartial game that can describe its components.
+*/
+
+use super::abstract_game::{AbstractGame, Ai};
+use super::player::Player;
+use super::random::GameRng;
+
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Rules of an impartial game, which say what the options of each
+/// component position are
+pub trait ImpartialRules: Clone + PartialEq {
+    /// A single component (e.g. a pile in Nim)
+    type Position: Clone + Eq + Hash;
+
+    /// Options of a component: the positions it can be moved to in one
+    /// move. A move can split a component, so each option is a sum of
+    /// components.
+    fn options(&self, pos: &Self::Position) -> Vec<Vec<Self::Position>>;
+}
+
+/// A game (for normal play) that is the disjunctive sum of its components
+pub trait ImpartialGame<const N: usize>: AbstractGame<N> {
+    type Rules: ImpartialRules;
+
+    fn rules(&self) -> Self::Rules;
+
+    /// Components of the current position; every legal move changes
+    /// exactly one of them
+    fn components(&self) -> Vec<<Self::Rules as ImpartialRules>::Position>;
+
+    /// Whether the game is in normal play and is the sum of its components
+    /// under the current rules. A game with variants that aren't (misère
+    /// play, or moves in several components at once) has no Grundy value.
+    fn is_normal_play_sum(&self) -> bool {
+        true
+    }
+}
+
+/// Least natural number not in values (minimum excludant)
+pub fn mex(values: &[usize]) -> usize {
+    (0..).find(|n| !values.contains(n)).unwrap()
+}
+
+/// Memoized Grundy values of the components of an impartial game
+pub struct GrundyTable<R: ImpartialRules> {
+    // Rules the values were worked out for
+    rules: Option<R>,
+    values: HashMap<R::Position, usize>,
+}
+
+impl<R: ImpartialRules> Default for GrundyTable<R> {
+    fn default() -> Self {
+        Self { rules: None, values: HashMap::new() }
+    }
+}
+
+impl<R: ImpartialRules> GrundyTable<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grundy value of a single component
+    pub fn grundy(&mut self, rules: &R, pos: &R::Position) -> usize {
+        if self.rules.as_ref() != Some(rules) {
+            *self = Self { rules: Some(rules.clone()), values: HashMap::new() };
+        }
+        self.grundy_memo(rules, pos)
+    }
+
+    /// Grundy value of a disjunctive sum of components
+    pub fn sum(&mut self, rules: &R, positions: &[R::Position]) -> usize {
+        positions.iter().fold(0, |acc, pos| acc ^ self.grundy(rules, pos))
+    }
+
+    /// Grundy value of the position of a game, which must be in normal
+    /// play and the sum of its components
+    pub fn game_value<G, const N: usize>(&mut self, game: &G) -> usize
+    where
+        G: ImpartialGame<N, Rules = R>,
+    {
+        assert!(
+            game.is_normal_play_sum(),
+            "a game that isn't a sum in normal play has no Grundy value"
+        );
+        self.sum(&game.rules(), &game.components())
+    }
+
+    // Works through the positions still to be valued with an explicit
+    // stack rather than by recursion, so that long chains of options (a
+    // large pile taken from a few sticks at a time) can't overflow the call
+    // stack
+    fn grundy_memo(&mut self, rules: &R, pos: &R::Position) -> usize {
+        let mut stack = vec![pos.clone()];
+        while let Some(top) = stack.last() {
+            if self.values.contains_key(top) {
+                stack.pop();
+                continue;
+            }
+            let options = rules.options(top);
+            let unknown: Vec<R::Position> = options
+                .iter()
+                .flatten()
+                .filter(|p| !self.values.contains_key(*p))
+                .cloned()
+                .collect();
+            if !unknown.is_empty() {
+                stack.extend(unknown);
+                continue;
+            }
+            let option_values: Vec<usize> = options
+                .iter()
+                .map(|option| {
+                    option.iter().fold(0, |acc, p| acc ^ self.values[p])
+                })
+                .collect();
+            let top = stack.pop().unwrap();
+            self.values.insert(top, mex(&option_values));
+        }
+        self.values[pos]
+    }
+}
+
+/// AI that plays any impartial game perfectly (in normal play, with two
+/// players): it moves to a position of Grundy value zero when it can, and
+/// otherwise plays a random move. It can't play variants of a game that
+/// aren't sums in normal play (see `ImpartialGame::is_normal_play_sum`).
+pub struct GrundyAi<G, const N: usize>
+where
+    G: ImpartialGame<N>,
+{
+    table: GrundyTable<G::Rules>,
+}
+
+impl<G, const N: usize> Ai<G, N> for GrundyAi<G, N>
+where
+    G: ImpartialGame<N, Observation = G> + Clone,
+    G::Move: Clone,
+{
+    fn new() -> Self {
+        Self { table: GrundyTable::new() }
+    }
+    fn ai_move(
+        &mut self,
+        obs: &G,
+        _plyr: Player<N>,
+        legal_moves: &[G::Move],
+        rng: &mut GameRng,
+    ) -> G::Move {
+        let winning = legal_moves.iter().find(|mv| {
+            let mut next = obs.clone();
+            next.make_move((*mv).clone());
+            self.table.game_value(&next) == 0
+        });
+        winning
+            .or_else(|| legal_moves.choose(rng))
+            .expect("there should be a legal move")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Subtraction game where 1, 3 or 4 may be taken from a pile
+    #[derive(Clone, PartialEq)]
+    struct Subtract134;
+
+    impl ImpartialRules for Subtract134 {
+        type Position = usize;
+
+        fn options(&self, &pile: &usize) -> Vec<Vec<usize>> {
+            [1, 3, 4]
+                .iter()
+                .filter(|&&take| take <= pile)
+                .map(|&take| vec![pile - take])
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_mex() {
+        assert_eq!(mex(&[]), 0);
+        assert_eq!(mex(&[1, 2]), 0);
+        assert_eq!(mex(&[0, 2, 1, 4]), 3);
+    }
+
+    #[test]
+    fn test_grundy_values() {
+        let mut table = GrundyTable::new();
+        let values: Vec<usize> =
+            (0..14).map(|pile| table.grundy(&Subtract134, &pile)).collect();
+        // The values repeat with period 7
+        assert_eq!(values, vec![0, 1, 0, 1, 2, 3, 2, 0, 1, 0, 1, 2, 3, 2]);
+        assert_eq!(table.sum(&Subtract134, &[4, 5]), 2 ^ 3);
+        assert_eq!(table.sum(&Subtract134, &[4, 6]), 0);
+    }
+
+    #[test]
+    fn test_large_pile() {
+        let mut table = GrundyTable::new();
+        // 100000 is five more than a multiple of 7
+        assert_eq!(table.grundy(&Subtract134, &100_000), 3);
+    }
+}
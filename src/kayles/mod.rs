@@ -0,0 +1,337 @@
+/*
+    Implementation of Kayles
+
+    The game starts with one or more rows of pins. On your turn you knock
+    down either one pin or two neighbouring pins, which may split a row in
+    two. Whoever knocks down the last pin wins.
+
+    Kayles is an impartial game whose components are the rows.
+*/
+
+use crate::abstract_game::{AbstractGame, GameStatus};
+use crate::ai::transposition::{self, PositionHash};
+use crate::console::Console;
+use crate::impartial::{ImpartialGame, ImpartialRules};
+use crate::player::TwoPlayers;
+use crate::random::GameRng;
+use crate::util;
+
+use std::fmt::{self, Display};
+
+// Itertools for .join()
+use itertools::Itertools;
+
+const NUM_PLAYERS: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct KaylesState {
+    // Lengths of the rows that still have pins (all nonzero)
+    rows: Vec<usize>,
+    to_move: TwoPlayers,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KaylesMove {
+    // Row, and first pin knocked down (1-indexed)
+    row: usize,
+    pin: usize,
+    // Number of pins knocked down: 1 or 2
+    count: usize,
+}
+impl KaylesMove {
+    /// Parse the format used by Display, e.g. "Knock down pins 3 and 4 of
+    /// row 1"
+    fn parse_display(raw: &str) -> Option<Self> {
+        let raw = raw.to_lowercase();
+        let words: Vec<&str> = raw.split_whitespace().collect();
+        let (pins, row) = match words.as_slice() {
+            ["knock", "down", "pin", pin, "of", "row", row] => {
+                (vec![*pin], row)
+            }
+            ["knock", "down", "pins", a, "and", b, "of", "row", row] => {
+                (vec![*a, *b], row)
+            }
+            _ => return None,
+        };
+        let pins = pins
+            .iter()
+            .map(|pin| pin.parse().ok())
+            .collect::<Option<Vec<usize>>>()?;
+        Self::from_pins(row.parse().ok()?, &pins).ok()
+    }
+
+    /// Move knocking down the given pins of a row
+    fn from_pins(row: usize, pins: &[usize]) -> Result<Self, String> {
+        match *pins {
+            [pin] => Ok(Self { row, pin, count: 1 }),
+            [a, b] if b == a + 1 => Ok(Self { row, pin: a, count: 2 }),
+            [_, _] => Err("The two pins should be next to each other, \
+                in order. "
+                .to_string()),
+            _ => Err("Knock down one or two pins. ".to_string()),
+        }
+    }
+}
+impl Display for KaylesMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count == 1 {
+            write!(f, "Knock down pin {} of row {}", self.pin, self.row)
+        } else {
+            write!(
+                f,
+                "Knock down pins {} and {} of row {}",
+                self.pin,
+                self.pin + 1,
+                self.row
+            )
+        }
+    }
+}
+
+/// The rules of Kayles, for working out Grundy values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KaylesRules;
+
+impl ImpartialRules for KaylesRules {
+    type Position = usize;
+
+    fn options(&self, &row: &usize) -> Vec<Vec<usize>> {
+        let mut result = Vec::new();
+        for count in 1..=2.min(row) {
+            for left in 0..=(row - count) {
+                let right = row - count - left;
+                result.push(
+                    [left, right].iter().copied().filter(|&n| n > 0).collect(),
+                );
+            }
+        }
+        result
+    }
+}
+
+impl AbstractGame<NUM_PLAYERS> for KaylesState {
+    const NAME: &'static str = "kayles";
+
+    type Move = KaylesMove;
+    type Observation = Self;
+    type SetupParams = Vec<usize>;
+
+    fn setup_from_user_input(console: &mut dyn Console) -> Vec<usize> {
+        util::from_user_input_parsing(
+            console,
+            "Number of pins in each row (e.g. '10' or '3 5 7')? ",
+            |raw| match util::parse_vec_usize(raw.trim()) {
+                Some(rows) if rows.iter().all(|&row| row > 0) => Ok(rows),
+                _ => {
+                    Err("Type positive integers separated by spaces. "
+                        .to_string())
+                }
+            },
+        )
+    }
+
+    fn format_params(rows: &Vec<usize>) -> String {
+        rows.iter().join(" ")
+    }
+
+    fn parse_params(raw: &str) -> Result<Vec<usize>, String> {
+        if raw.trim().is_empty() {
+            Ok(Vec::new())
+        } else {
+            util::parse_vec_usize(raw.trim())
+                .ok_or_else(|| "Rows should be integers. ".to_string())
+        }
+    }
+
+    fn game_setup(rows: Vec<usize>, _rng: &mut GameRng) -> Self {
+        let rows = rows.into_iter().filter(|&row| row > 0).collect();
+        Self { rows, to_move: TwoPlayers::ONE }
+    }
+
+    fn status(&self) -> GameStatus<NUM_PLAYERS> {
+        if self.rows.is_empty() {
+            GameStatus::Won(self.to_move.opponent())
+        } else {
+            GameStatus::ToMove(self.to_move)
+        }
+    }
+
+    fn query(&self) -> String {
+        "Choose a row and one or two neighbouring pins: ".to_string()
+    }
+
+    fn parse_move(&self, raw: &str) -> Result<KaylesMove, String> {
+        if let Some(mv) = KaylesMove::parse_display(raw) {
+            return Ok(mv);
+        }
+        let ints = util::parse_vec_usize(raw.trim()).ok_or_else(|| {
+            "Move should be a row followed by one or two pins. ".to_string()
+        })?;
+        match ints.split_first() {
+            Some((&row, pins)) => KaylesMove::from_pins(row, pins),
+            None => Err("Move should be a row followed by one or two pins. "
+                .to_string()),
+        }
+    }
+
+    fn check_move(&self, mv: &KaylesMove) -> Result<(), String> {
+        if mv.row == 0 || mv.row > self.rows.len() {
+            Err(format!("Row should be between 1 and {}. ", self.rows.len()))
+        } else if mv.count == 0 || mv.count > 2 {
+            Err("Knock down one or two pins. ".to_string())
+        } else if mv.pin == 0 || mv.pin + mv.count - 1 > self.rows[mv.row - 1] {
+            Err(format!(
+                "Pins in row {} are numbered 1 to {}. ",
+                mv.row,
+                self.rows[mv.row - 1]
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<KaylesMove> {
+        let mut result = Vec::new();
+        for (i, &len) in self.rows.iter().enumerate() {
+            for count in 1..=2.min(len) {
+                for pin in 1..=(len - count + 1) {
+                    result.push(KaylesMove { row: i + 1, pin, count });
+                }
+            }
+        }
+        result
+    }
+
+    fn make_move(&mut self, mv: KaylesMove) {
+        debug_assert!(self.is_valid_move(&mv));
+        let len = self.rows[mv.row - 1];
+        let left = mv.pin - 1;
+        let right = len - left - mv.count;
+        let parts: Vec<usize> =
+            [left, right].iter().copied().filter(|&n| n > 0).collect();
+        self.rows.splice(mv.row - 1..mv.row, parts);
+        self.to_move = self.to_move.opponent();
+    }
+
+    fn print_state_visible(&self, _plyr: TwoPlayers) -> String {
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| format!("Row {}: {}", i + 1, (1..=len).join(" ")))
+            .join("\n")
+    }
+
+    fn observe(&self, _plyr: TwoPlayers) -> Self {
+        self.clone()
+    }
+}
+
+impl ImpartialGame<NUM_PLAYERS> for KaylesState {
+    type Rules = KaylesRules;
+
+    fn rules(&self) -> KaylesRules {
+        KaylesRules
+    }
+
+    fn components(&self) -> Vec<usize> {
+        self.rows.clone()
+    }
+}
+
+impl PositionHash<NUM_PLAYERS> for KaylesState {
+    fn position_hash(&self) -> u64 {
+        // The order of the rows doesn't matter
+        let mut rows = self.rows.clone();
+        rows.sort_unstable();
+        transposition::hash_value(&(rows, self.to_move))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_game::Ai;
+    use crate::impartial::{GrundyAi, GrundyTable};
+    use crate::random;
+
+    fn new_game(rows: Vec<usize>) -> KaylesState {
+        KaylesState::game_setup(rows, &mut random::seeded(0))
+    }
+
+    #[test]
+    fn test_split_row() {
+        let mut game = new_game(vec![3, 6]);
+        game.make_move(KaylesMove { row: 2, pin: 3, count: 2 });
+        assert_eq!(game.rows, vec![3, 2, 2]);
+        game.make_move(KaylesMove { row: 1, pin: 1, count: 1 });
+        assert_eq!(game.rows, vec![2, 2, 2]);
+        game.make_move(KaylesMove { row: 3, pin: 1, count: 2 });
+        assert_eq!(game.rows, vec![2, 2]);
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::TWO));
+        assert_eq!(
+            game.print_state_visible(TwoPlayers::ONE),
+            "Row 1: 1 2\nRow 2: 1 2"
+        );
+    }
+
+    #[test]
+    fn test_legal_moves_agree() {
+        let mut game = new_game(vec![1, 4]);
+        while !game.is_ended() {
+            for row in 0..=3 {
+                for pin in 0..=5 {
+                    for count in 0..=3 {
+                        let mv = KaylesMove { row, pin, count };
+                        assert!(game.legal_moves_agree(&mv));
+                    }
+                }
+            }
+            let mv = game.legal_moves().pop().unwrap();
+            game.make_move(mv);
+        }
+        assert_eq!(game.status(), GameStatus::Won(TwoPlayers::ONE));
+    }
+
+    #[test]
+    fn test_parse_move_round_trip() {
+        let game = new_game(vec![5]);
+        for mv in game.legal_moves() {
+            assert_eq!(game.parse_move(&mv.to_string()), Ok(mv));
+        }
+        assert_eq!(
+            game.parse_move("1 2 3"),
+            Ok(KaylesMove { row: 1, pin: 2, count: 2 })
+        );
+        assert!(game.parse_move("1 2 4").is_err());
+    }
+
+    #[test]
+    fn test_grundy_values() {
+        let mut table = GrundyTable::new();
+        let values: Vec<usize> =
+            (0..16).map(|row| table.grundy(&KaylesRules, &row)).collect();
+        assert_eq!(
+            values,
+            vec![0, 1, 2, 3, 1, 4, 3, 2, 1, 4, 2, 6, 4, 1, 2, 7]
+        );
+    }
+
+    #[test]
+    fn test_grundy_ai_wins() {
+        let mut rng = random::seeded(0);
+        let mut game = new_game(vec![4, 6, 9]);
+        let mut table = GrundyTable::new();
+        assert_ne!(table.game_value(&game), 0);
+        let mut ai = GrundyAi::<KaylesState, 2>::new();
+        while !game.is_ended() {
+            let legal_moves = game.legal_moves();
+            let mv = if game.to_move == TwoPlayers::ONE {
+                ai.ai_move(&game, TwoPlayers::ONE, &legal_moves, &mut rng)
+            } else {
+                legal_moves[0].clone()
+            };
+            game.make_move(mv);
+        }
+        assert_eq!(game.status(), GameStatus::Won(TwoPlayers::ONE));
+    }
+}
@@ -1,10 +1,19 @@
 // Abstract interfaces and support for defining/running games
 pub mod abstract_game;
+pub mod ai;
+pub mod console;
+pub mod history;
+pub mod impartial;
 pub mod play;
 pub mod player;
+pub mod random;
+pub mod record;
 pub mod util;
 pub mod view;
 
 // Games implemented
 pub mod battleship;
+pub mod kayles;
 pub mod nim;
+pub mod pig;
+pub mod rps;
@@ -0,0 +1,253 @@
+/*
+    Perfect play for (two-player) Nim and its variants
+
+    The AI looks for a move to a losing position for the opponent (a
+    P-position). Whether a position is losing is known in closed form for:
+    - ordinary Nim: the nim-sum of the piles is zero
+    - normal play from one pile at a time, with restricted takes: the XOR of
+      the Grundy values of the piles is zero (see impartial.rs)
+    - misère Nim: as ordinary Nim, except when every pile has at most one
+      stick, when an odd number of piles must be left
+    - Moore's Nim (up to k piles at once): in binary, the number of piles
+      with each bit set is a multiple of k + 1
+    For other combinations of rules, the AI searches the game tree,
+    remembering the result for each position.
+*/
+
+use super::{NimMove, NimRules, NimState};
+use crate::abstract_game::{AbstractGame, Ai};
+use crate::impartial::GrundyTable;
+use crate::player::Player;
+use crate::random::GameRng;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Nim-sum: the XOR of the pile sizes. The player to move (in two-player
+/// Nim) can win exactly when this is nonzero.
+pub fn nim_sum(piles: &[usize]) -> usize {
+    piles.iter().fold(0, |acc, &pile| acc ^ pile)
+}
+
+/// AI that plays Nim perfectly (for two players), under any rules: it
+/// moves to a losing position for the opponent when it can, and otherwise
+/// takes as few sticks as possible from the largest pile, to drag the game
+/// out and give the opponent chances to go wrong
+#[derive(Default)]
+pub struct NimAi {
+    // Rules the values below were worked out for
+    rules: NimRules,
+    grundy: GrundyTable<NimRules>,
+    // Whether each position (with piles sorted) is losing for the player
+    // to move, for positions found by searching
+    losing: HashMap<Vec<usize>, bool>,
+}
+
+impl NimAi {
+    /// Whether the player to move loses with best play
+    fn is_losing<const N: usize>(&mut self, game: &NimState<N>) -> bool {
+        let rules = &self.rules;
+        let piles = &game.piles;
+        if rules.is_unrestricted() && rules.max_piles == 1 && !rules.misere {
+            // Ordinary Nim
+            return nim_sum(piles) == 0;
+        }
+        if rules.max_piles == 1 && !rules.misere {
+            return self.grundy.game_value(game) == 0;
+        }
+        if rules.is_unrestricted() && rules.max_piles == 1 {
+            // Misère Nim
+            return if piles.iter().all(|&pile| pile <= 1) {
+                nim_sum(piles) == 1
+            } else {
+                nim_sum(piles) == 0
+            };
+        }
+        if rules.is_unrestricted() && !rules.misere {
+            // Moore's Nim
+            let max_bits = usize::BITS
+                - piles.iter().max().map_or(0, |p| p.leading_zeros());
+            return (0..max_bits).all(|bit| {
+                let count =
+                    piles.iter().filter(|&&p| p >> bit & 1 == 1).count();
+                count % (rules.max_piles + 1) == 0
+            });
+        }
+        self.search(game)
+    }
+
+    /// Whether the player to move loses with best play, by searching
+    fn search<const N: usize>(&mut self, game: &NimState<N>) -> bool {
+        let mut key = game.piles.clone();
+        key.sort_unstable();
+        if let Some(&losing) = self.losing.get(&key) {
+            return losing;
+        }
+        let moves = game.legal_moves();
+        let losing = if moves.is_empty() {
+            // The previous player made the last move
+            !self.rules.misere
+        } else {
+            moves.into_iter().all(|mv| {
+                let mut next = game.clone();
+                next.make_move(mv);
+                !self.search(&next)
+            })
+        };
+        self.losing.insert(key, losing);
+        losing
+    }
+
+    fn best_move<const N: usize>(
+        &mut self,
+        game: &NimState<N>,
+        legal_moves: &[NimMove],
+    ) -> NimMove {
+        if game.rules != self.rules {
+            *self = Self { rules: game.rules.clone(), ..Self::default() };
+        }
+        let winning = legal_moves.iter().find(|mv| {
+            let mut next = game.clone();
+            next.make_move((*mv).clone());
+            self.is_losing(&next)
+        });
+        let stalling = || {
+            legal_moves
+                .iter()
+                .min_by_key(|mv| {
+                    let (pile, _) = mv.takes[0];
+                    (mv.total(), Reverse(game.piles[pile - 1]))
+                })
+                .expect("there should be a legal move")
+        };
+        winning.unwrap_or_else(stalling).clone()
+    }
+}
+
+impl<const N: usize> Ai<NimState<N>, N> for NimAi {
+    fn new() -> Self {
+        Self::default()
+    }
+    fn ai_move(
+        &mut self,
+        obs: &NimState<N>,
+        _plyr: Player<N>,
+        legal_moves: &[NimMove],
+        _rng: &mut GameRng,
+    ) -> NimMove {
+        self.best_move(obs, legal_moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_game::GameStatus;
+    use crate::nim::NimParams;
+    use crate::random;
+
+    fn new_game(piles: Vec<usize>, rules: &str) -> NimState<2> {
+        let params = NimParams { piles, rules: rules.parse().unwrap() };
+        NimState::game_setup(params, &mut random::seeded(0))
+    }
+
+    /// Whether the player to move loses, by searching without any theory
+    fn brute_force_losing(game: &NimState<2>) -> bool {
+        let moves = game.legal_moves();
+        if moves.is_empty() {
+            return !game.rules.misere;
+        }
+        moves.into_iter().all(|mv| {
+            let mut next = game.clone();
+            next.make_move(mv);
+            !brute_force_losing(&next)
+        })
+    }
+
+    /// Every position with up to three piles of up to four sticks
+    fn small_positions() -> Vec<Vec<usize>> {
+        let mut result = Vec::new();
+        for a in 0..=4 {
+            for b in 0..=4 {
+                for c in 0..=4 {
+                    result.push(vec![a, b, c]);
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_closed_forms() {
+        for rules in &["", "misere", "max-take 2", "takes 1 3", "piles 2"] {
+            let mut ai =
+                NimAi { rules: rules.parse().unwrap(), ..NimAi::default() };
+            for piles in small_positions() {
+                let game = new_game(piles.clone(), rules);
+                assert_eq!(
+                    ai.is_losing(&game),
+                    brute_force_losing(&game),
+                    "rules '{}', piles {:?}",
+                    rules,
+                    piles
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_winning_move() {
+        let game = new_game(vec![3, 4, 5], "");
+        let mv = NimAi::default().best_move(&game, &game.legal_moves());
+        assert_eq!(mv, NimMove::new(1, 2));
+        let mut piles = vec![3, 4, 5];
+        piles[0] -= 2;
+        assert_eq!(nim_sum(&piles), 0);
+    }
+
+    #[test]
+    fn test_large_piles() {
+        // Ordinary Nim needs only the nim-sum, however large the piles
+        let game = new_game(vec![100_000, 3], "");
+        let mv = NimAi::default().best_move(&game, &game.legal_moves());
+        assert_eq!(mv, NimMove::new(1, 100_000 - 3));
+    }
+
+    #[test]
+    fn test_stalls_when_losing() {
+        let game = new_game(vec![1, 2, 3], "");
+        let mv = NimAi::default().best_move(&game, &game.legal_moves());
+        assert_eq!(mv, NimMove::new(3, 1));
+    }
+
+    #[test]
+    fn test_wins_from_winning_positions() {
+        let variants = [
+            (vec![3, 4, 5], ""),
+            (vec![3, 4, 5], "misere"),
+            (vec![2, 2, 4], "misere; max-take 2"),
+            (vec![7, 6], "takes 1 3 4"),
+            (vec![1, 2, 4], "piles 2"),
+        ];
+        for (piles, rules) in &variants {
+            let mut rng = random::seeded(0);
+            let mut game = new_game(piles.clone(), rules);
+            assert!(!brute_force_losing(&game), "{}", rules);
+            let mut ai = NimAi::default();
+            let ai_player = Player::from_index(0).unwrap();
+            while !game.is_ended() {
+                let plyr = game.cur_player().unwrap();
+                let legal_moves = game.legal_moves();
+                let mv = if plyr == ai_player {
+                    ai.ai_move(&game, plyr, &legal_moves, &mut rng)
+                } else {
+                    // The opponent takes as much as possible
+                    legal_moves.last().unwrap().clone()
+                };
+                assert!(game.is_valid_move(&mv));
+                game.make_move(mv);
+            }
+            assert_eq!(game.status(), GameStatus::Won(ai_player), "{}", rules);
+        }
+    }
+}
@@ -1,45 +1,292 @@
 /*
     Implementation of the game of Nim
+
+    Besides normal play (whoever takes the last stick wins), the rules can
+    be varied when setting up the game:
+    - misère play: whoever takes the last stick loses
+    - a maximum number of sticks to take from a pile in one move
+    - Moore's Nim: sticks may be taken from up to k piles in one move
+    - a fixed set of numbers of sticks that may be taken (subtraction games)
+    If sticks are left but no move is allowed, the game ends just as if the
+    last stick had been taken.
+
+    In normal play taking from one pile at a time, Nim is an impartial game
+    whose components are the piles.
 */
 
+pub mod ai;
+
+pub use ai::NimAi;
+
 use crate::abstract_game::{AbstractGame, GameStatus};
-use crate::player::Player;
+use crate::ai::transposition::{self, PositionHash};
+use crate::console::Console;
+use crate::impartial::{ImpartialGame, ImpartialRules};
+use crate::player::{Player, PlayerSet};
+use crate::random::GameRng;
 use crate::util;
 
 use std::fmt::{self, Display};
+use std::str::FromStr;
+
+// Itertools for .join()
+use itertools::Itertools;
+
+/// Variations on the rules of Nim
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NimRules {
+    /// Whether the player who takes the last stick loses
+    pub misere: bool,
+    /// Most sticks that may be taken from a pile in one move
+    pub max_take: Option<usize>,
+    /// Most piles that may be taken from in one move (1 in ordinary Nim)
+    pub max_piles: usize,
+    /// Numbers of sticks that may be taken from a pile, if restricted
+    pub allowed_takes: Option<Vec<usize>>,
+}
+impl Default for NimRules {
+    fn default() -> Self {
+        Self {
+            misere: false,
+            max_take: None,
+            max_piles: 1,
+            allowed_takes: None,
+        }
+    }
+}
+impl NimRules {
+    /// Whether the rules allow taking this many sticks from a pile
+    pub fn allows_take(&self, take: usize) -> bool {
+        take > 0
+            && !matches!(self.max_take, Some(max) if take > max)
+            && !matches!(&self.allowed_takes, Some(ts) if !ts.contains(&take))
+    }
+
+    /// Numbers of sticks that may be taken from a pile of this size
+    pub fn takes_from(&self, pile: usize) -> impl Iterator<Item = usize> + '_ {
+        (1..=pile).filter(move |&take| self.allows_take(take))
+    }
+
+    /// Whether any number of sticks may be taken from a pile
+    pub fn is_unrestricted(&self) -> bool {
+        self.max_take.is_none() && self.allowed_takes.is_none()
+    }
+}
+/// Rules in the format "misere; max-take 3; piles 2; takes 1 3 4", listing
+/// only the rules that differ from ordinary Nim
+impl Display for NimRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.misere {
+            parts.push("misere".to_string());
+        }
+        if let Some(max) = self.max_take {
+            parts.push(format!("max-take {}", max));
+        }
+        if self.max_piles != 1 {
+            parts.push(format!("piles {}", self.max_piles));
+        }
+        if let Some(takes) = &self.allowed_takes {
+            let takes: Vec<String> =
+                takes.iter().map(|take| take.to_string()).collect();
+            parts.push(format!("takes {}", takes.join(" ")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+impl FromStr for NimRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let positive = |raw: &str| match raw.trim().parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Expected a positive integer, not {}. ", raw)),
+        };
+        let mut rules = Self::default();
+        for part in s.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, arg) = part.split_once(' ').unwrap_or((part, ""));
+            match name {
+                "misere" => rules.misere = true,
+                "max-take" => rules.max_take = Some(positive(arg)?),
+                "piles" => rules.max_piles = positive(arg)?,
+                "takes" => {
+                    let takes = arg
+                        .split_whitespace()
+                        .map(positive)
+                        .collect::<Result<Vec<usize>, String>>()?;
+                    if takes.is_empty() {
+                        return Err("List the numbers of sticks that may be \
+                            taken. "
+                            .to_string());
+                    }
+                    rules.allowed_takes = Some(takes);
+                }
+                _ => return Err(format!("Unknown rule: {}. ", part)),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// Options of a pile when taking from one pile at a time (the Grundy
+/// values are only meaningful in normal play)
+impl ImpartialRules for NimRules {
+    type Position = usize;
+
+    fn options(&self, &pile: &usize) -> Vec<Vec<usize>> {
+        self.takes_from(pile).map(|take| vec![pile - take]).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NimParams {
+    pub piles: Vec<usize>,
+    pub rules: NimRules,
+}
+impl NimParams {
+    /// Ordinary Nim with the given piles
+    pub fn new(piles: Vec<usize>) -> Self {
+        Self { piles, rules: NimRules::default() }
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NimState<const N: usize> {
     piles: Vec<usize>,
-    total_sticks: usize,
+    rules: NimRules,
     to_move: Player<N>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NimMove {
-    // Note: pile should be >= 1 (uses 1-indexing)
-    pile: usize,
-    take: usize,
+    // Pile and number of sticks, for each pile taken from, in increasing
+    // order of pile
+    // Note: piles should be >= 1 (uses 1-indexing)
+    takes: Vec<(usize, usize)>,
+}
+impl NimMove {
+    /// Take sticks from a single pile
+    pub fn new(pile: usize, take: usize) -> Self {
+        Self { takes: vec![(pile, take)] }
+    }
+
+    /// Parse the format used by Display, e.g. "Take 2 from pile 1" or
+    /// "Take 2 from pile 1 and 3 from pile 2"
+    fn parse_display(raw: &str) -> Option<Self> {
+        let words: Vec<&str> = raw.split_whitespace().collect();
+        let (take_word, rest) = words.split_first()?;
+        if !take_word.eq_ignore_ascii_case("take") {
+            return None;
+        }
+        let takes = rest
+            .split(|&word| word == "and")
+            .map(|part| match part {
+                [take, "from", "pile", pile] => {
+                    Some((pile.parse().ok()?, take.parse().ok()?))
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<(usize, usize)>>>()?;
+        Some(Self::from_takes(takes))
+    }
+
+    fn from_takes(mut takes: Vec<(usize, usize)>) -> Self {
+        takes.sort_unstable();
+        Self { takes }
+    }
+
+    /// Total number of sticks taken
+    fn total(&self) -> usize {
+        self.takes.iter().map(|&(_, take)| take).sum()
+    }
 }
 impl Display for NimMove {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Take {} from pile {}", self.take, self.pile)
+        let takes: Vec<String> = self
+            .takes
+            .iter()
+            .map(|(pile, take)| format!("{} from pile {}", take, pile))
+            .collect();
+        write!(f, "Take {}", takes.join(" and "))
+    }
+}
+
+impl<const N: usize> NimState<N> {
+    fn has_move(&self) -> bool {
+        self.piles
+            .iter()
+            .any(|&pile| self.rules.takes_from(pile).next().is_some())
+    }
+
+    /// Add to result every move that takes from piles after the first
+    /// `from`, in addition to the given takes
+    fn extend_moves(
+        &self,
+        from: usize,
+        takes: &mut Vec<(usize, usize)>,
+        result: &mut Vec<NimMove>,
+    ) {
+        if !takes.is_empty() {
+            result.push(NimMove { takes: takes.clone() });
+        }
+        if takes.len() == self.rules.max_piles {
+            return;
+        }
+        for (i, &pile) in self.piles.iter().enumerate().skip(from) {
+            for take in self.rules.takes_from(pile) {
+                takes.push((i + 1, take));
+                self.extend_moves(i + 1, takes, result);
+                takes.pop();
+            }
+        }
+    }
+
+    fn check_take(&self, pile: usize, take: usize) -> Result<(), String> {
+        // Pile uses one indexing
+        if pile == 0 || pile > self.piles.len() {
+            Err(format!(
+                "Pile should be between {} and {}. ",
+                1,
+                self.piles.len()
+            ))
+        } else if take == 0 {
+            Err("Must take at least one stick. ".to_string())
+        } else if take > self.piles[pile - 1] {
+            Err("Not enough sticks in that pile. ".to_string())
+        } else if matches!(self.rules.max_take, Some(max) if take > max) {
+            Err(format!(
+                "Can take at most {} sticks from a pile. ",
+                self.rules.max_take.unwrap()
+            ))
+        } else if !self.rules.allows_take(take) {
+            Err(format!(
+                "The numbers of sticks you may take are: {}. ",
+                self.rules.allowed_takes.as_ref().unwrap().iter().join(", ")
+            ))
+        } else {
+            Ok(())
+        }
     }
 }
 
 impl<const N: usize> AbstractGame<N> for NimState<N> {
+    const NAME: &'static str = "nim";
+
     type Move = NimMove;
-    type SetupParams = Vec<usize>;
+    type Observation = Self;
+    type SetupParams = NimParams;
 
-    fn setup_from_user_input() -> Vec<usize> {
+    fn setup_from_user_input(console: &mut dyn Console) -> NimParams {
         let mut piles: Vec<usize> = Vec::new();
         let num_piles: usize = util::from_user_input(
+            console,
             "Number of piles? ",
             "Type a nonnegative integer. ",
         );
         for i in 1..=num_piles {
             let query = format!("Pile {} size? ", i);
             piles.push(util::from_user_input_satisfying(
+                console,
                 &query,
                 "Type a positive integer. ",
                 "Type a positive integer. ",
@@ -47,66 +294,307 @@ impl<const N: usize> AbstractGame<N> for NimState<N> {
             ));
         }
         debug_assert_eq!(piles.len(), num_piles);
-        println!("Piles: {:?}", piles);
+        console.println(&format!("Piles: {:?}", piles));
+
+        let rules = util::from_user_input_parsing(
+            console,
+            "Rules? Press enter for ordinary Nim, or list any of 'misere', \
+            'max-take <n>', 'piles <k>' and 'takes <n> <n> ...', separated \
+            by semicolons: ",
+            |raw| raw.parse(),
+        );
+        NimParams { piles, rules }
+    }
+
+    fn format_params(params: &NimParams) -> String {
+        let piles = params.piles.iter().join(" ");
+        if params.rules == NimRules::default() {
+            piles
+        } else {
+            format!("{}; {}", piles, params.rules)
+        }
+    }
 
-        piles
+    fn parse_params(raw: &str) -> Result<NimParams, String> {
+        let (piles, rules) = raw.split_once(';').unwrap_or((raw, ""));
+        let piles = if piles.trim().is_empty() {
+            Vec::new()
+        } else {
+            util::parse_vec_usize(piles.trim())
+                .ok_or_else(|| "Piles should be integers. ".to_string())?
+        };
+        if piles.contains(&0) {
+            return Err("Piles should be positive. ".to_string());
+        }
+        Ok(NimParams { piles, rules: rules.parse()? })
     }
 
-    fn game_setup(piles: Vec<usize>) -> Self {
-        let total_sticks = piles.iter().sum();
+    fn game_setup(params: NimParams, _rng: &mut GameRng) -> Self {
         let to_move = Player::from_index(0).unwrap();
-        Self { piles, total_sticks, to_move }
+        Self { piles: params.piles, rules: params.rules, to_move }
     }
 
     fn status(&self) -> GameStatus<N> {
-        if self.total_sticks == 0 {
-            GameStatus::Won(self.to_move.prev_player())
-        } else {
-            GameStatus::ToMove(self.to_move)
+        if self.has_move() {
+            return GameStatus::ToMove(self.to_move);
+        }
+        let last = self.to_move.prev_player();
+        if !self.rules.misere {
+            return GameStatus::Won(last);
+        }
+        // In misère play, everyone except the last player to move wins
+        let winners: PlayerSet<N> =
+            Player::all().filter(|&plyr| plyr != last).collect();
+        match winners.iter().collect::<Vec<_>>().as_slice() {
+            [winner] => GameStatus::Won(*winner),
+            _ => GameStatus::SharedWin(winners),
         }
     }
 
     fn query(&self) -> String {
-        "Choose a pile and number of sticks: ".to_string()
+        if self.rules.max_piles == 1 {
+            "Choose a pile and number of sticks: ".to_string()
+        } else {
+            format!(
+                "Choose up to {} piles, each followed by a number of sticks: ",
+                self.rules.max_piles
+            )
+        }
     }
 
     fn parse_move(&self, raw: &str) -> Result<NimMove, String> {
+        if let Some(mv) = NimMove::parse_display(raw) {
+            return Ok(mv);
+        }
         let ints = util::parse_vec_usize(raw).ok_or_else(|| {
             "Move should be two integers separated by a space. ".to_string()
         })?;
         if ints.len() == 2 {
-            Ok(NimMove { pile: ints[0], take: ints[1] })
+            Ok(NimMove::new(ints[0], ints[1]))
+        } else if self.rules.max_piles > 1 && ints.len() % 2 == 0 {
+            let takes = ints.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+            Ok(NimMove::from_takes(takes))
+        } else if self.rules.max_piles > 1 {
+            Err("Move should be pairs of integers: a pile, then a number of \
+                sticks. "
+                .to_string())
         } else {
             Err("Move should be exactly two integers. ".to_string())
         }
     }
 
     fn check_move(&self, mv: &NimMove) -> Result<(), String> {
-        // Pile uses one indexing
-        if mv.pile == 0 || mv.pile > self.piles.len() {
-            Err(format!(
-                "Pile should be between {} and {}. ",
-                1,
-                self.piles.len()
-            ))
-        } else if mv.take == 0 {
-            Err("Must take at least one stick. ".to_string())
-        } else if mv.take > self.piles[mv.pile - 1] {
-            Err("Not enough sticks in that pile. ".to_string())
-        } else {
-            Ok(())
+        if mv.takes.is_empty() {
+            return Err("Must take at least one stick. ".to_string());
         }
+        if mv.takes.len() > self.rules.max_piles {
+            return Err(format!(
+                "Can take from at most {} piles. ",
+                self.rules.max_piles
+            ));
+        }
+        if mv.takes.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err("Take from each pile at most once. ".to_string());
+        }
+        for &(pile, take) in &mv.takes {
+            self.check_take(pile, take)?;
+        }
+        Ok(())
+    }
+
+    fn legal_moves(&self) -> Vec<NimMove> {
+        let mut result = Vec::new();
+        self.extend_moves(0, &mut Vec::new(), &mut result);
+        result
     }
 
     fn make_move(&mut self, mv: NimMove) {
-        debug_assert!(mv.pile >= 1);
-        debug_assert!(mv.pile <= self.piles.len());
-        self.piles[mv.pile - 1] -= mv.take;
-        self.total_sticks -= mv.take;
+        for (pile, take) in mv.takes {
+            debug_assert!(pile >= 1);
+            debug_assert!(pile <= self.piles.len());
+            self.piles[pile - 1] -= take;
+        }
         self.to_move.advance_player();
     }
 
     fn print_state_visible(&self, _plyr: Player<N>) -> String {
-        format!("Piles: {:?}", self.piles)
+        if self.rules == NimRules::default() {
+            format!("Piles: {:?}", self.piles)
+        } else {
+            format!("Piles: {:?}\nRules: {}", self.piles, self.rules)
+        }
+    }
+
+    fn observe(&self, _plyr: Player<N>) -> Self {
+        self.clone()
+    }
+}
+
+/// Nim is the sum of its piles only in normal play, taking from one pile
+/// at a time: misère Nim and Moore's Nim have no Grundy values
+impl<const N: usize> ImpartialGame<N> for NimState<N> {
+    type Rules = NimRules;
+
+    fn rules(&self) -> NimRules {
+        self.rules.clone()
+    }
+
+    fn components(&self) -> Vec<usize> {
+        self.piles.clone()
+    }
+
+    fn is_normal_play_sum(&self) -> bool {
+        !self.rules.misere && self.rules.max_piles == 1
+    }
+}
+
+impl<const N: usize> PositionHash<N> for NimState<N> {
+    fn position_hash(&self) -> u64 {
+        // The order of the piles doesn't matter, nor do empty piles
+        let mut piles: Vec<usize> =
+            self.piles.iter().copied().filter(|&pile| pile > 0).collect();
+        piles.sort_unstable();
+        transposition::hash_value(&(piles, self.to_move))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random;
+
+    fn new_game<const N: usize>(piles: Vec<usize>, rules: &str) -> NimState<N> {
+        let params = NimParams { piles, rules: rules.parse().unwrap() };
+        NimState::game_setup(params, &mut random::seeded(0))
+    }
+
+    fn all_moves_up_to(max_pile: usize, max_take: usize) -> Vec<NimMove> {
+        let mut result = Vec::new();
+        for pile in 0..=max_pile {
+            for take in 0..=max_take {
+                result.push(NimMove::new(pile, take));
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let game = new_game::<2>(vec![1, 3], "");
+        assert_eq!(
+            game.legal_moves(),
+            vec![
+                NimMove::new(1, 1),
+                NimMove::new(2, 1),
+                NimMove::new(2, 2),
+                NimMove::new(2, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_move_round_trip() {
+        let game = new_game::<2>(vec![5, 5], "piles 2");
+        let mv = NimMove::new(2, 4);
+        assert_eq!(game.parse_move(&mv.to_string()), Ok(mv.clone()));
+        assert_eq!(game.parse_move("2 4"), Ok(mv));
+        let mv = NimMove::from_takes(vec![(1, 3), (2, 1)]);
+        assert_eq!(mv.to_string(), "Take 3 from pile 1 and 1 from pile 2");
+        assert_eq!(game.parse_move(&mv.to_string()), Ok(mv.clone()));
+        assert_eq!(game.parse_move("2 1 1 3"), Ok(mv));
+    }
+
+    #[test]
+    fn test_legal_moves_agree() {
+        let mut game = new_game::<3>(vec![2, 0, 4], "");
+        while !game.is_ended() {
+            for mv in all_moves_up_to(4, 5) {
+                assert!(game.legal_moves_agree(&mv));
+            }
+            let mv = game.legal_moves().pop().unwrap();
+            game.make_move(mv);
+        }
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        let raw = "3 4 5; misere; max-take 3; piles 2; takes 1 3 4";
+        let params = NimState::<2>::parse_params(raw).unwrap();
+        assert!(params.rules.misere);
+        assert_eq!(params.rules.max_take, Some(3));
+        assert_eq!(params.rules.max_piles, 2);
+        assert_eq!(params.rules.allowed_takes, Some(vec![1, 3, 4]));
+        assert_eq!(NimState::<2>::format_params(&params), raw);
+        let ordinary = NimState::<2>::parse_params("3 4 5").unwrap();
+        assert_eq!(ordinary, NimParams::new(vec![3, 4, 5]));
+        assert_eq!(NimState::<2>::format_params(&ordinary), "3 4 5");
+        assert!(NimState::<2>::parse_params("3; sideways").is_err());
+        assert!(NimState::<2>::parse_params("3 0 5").is_err());
+    }
+
+    #[test]
+    fn test_misere() {
+        let mut game = new_game::<2>(vec![2], "misere");
+        // Taking both sticks loses, though it would win in normal play, so
+        // misère Nim can't be played by Grundy values
+        assert!(!game.is_normal_play_sum());
+        game.make_move(NimMove::new(1, 2));
+        assert_eq!(
+            game.status(),
+            GameStatus::Won(Player::from_index(1).unwrap())
+        );
+        let mut game = new_game::<3>(vec![1], "misere");
+        game.make_move(NimMove::new(1, 1));
+        let winners = [1, 2].iter().map(|&i| Player::from_index(i).unwrap());
+        assert_eq!(game.status(), GameStatus::SharedWin(winners.collect()));
+    }
+
+    #[test]
+    fn test_restricted_takes() {
+        let game = new_game::<2>(vec![5], "max-take 3; takes 2 3 4");
+        assert_eq!(
+            game.legal_moves(),
+            vec![NimMove::new(1, 2), NimMove::new(1, 3)]
+        );
+        assert!(game.check_move(&NimMove::new(1, 4)).is_err());
+        assert!(game.check_move(&NimMove::new(1, 1)).is_err());
+        for mv in all_moves_up_to(2, 6) {
+            assert!(game.legal_moves_agree(&mv));
+        }
+        // With one stick left and no way to take it, the game is over
+        let mut game = new_game::<2>(vec![3], "takes 2");
+        game.make_move(NimMove::new(1, 2));
+        assert_eq!(
+            game.status(),
+            GameStatus::Won(Player::from_index(0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_moores_nim() {
+        let game = new_game::<2>(vec![1, 1, 2], "piles 2");
+        assert_eq!(game.legal_moves().len(), 4 + 5);
+        let three_piles = NimMove::from_takes(vec![(1, 1), (2, 1), (3, 1)]);
+        assert!(game.check_move(&three_piles).is_err());
+        let duplicate = NimMove { takes: vec![(1, 1), (1, 1)] };
+        assert!(game.check_move(&duplicate).is_err());
+        assert!(game.legal_moves_agree(&three_piles));
+        assert!(game.legal_moves_agree(&duplicate));
+        assert!(!game.is_normal_play_sum());
+        assert!(new_game::<2>(vec![1, 1, 2], "takes 1 2").is_normal_play_sum());
+    }
+
+    #[test]
+    fn test_position_hash() {
+        let mut a = new_game::<2>(vec![3, 4, 5], "");
+        let mut b = new_game::<2>(vec![5, 3, 4], "");
+        assert_eq!(a.position_hash(), b.position_hash());
+        // Different moves to positions that are the same up to order
+        a.make_move(NimMove::new(1, 3));
+        b.make_move(NimMove::new(2, 3));
+        assert_eq!(a.position_hash(), b.position_hash());
+        a.make_move(NimMove::new(2, 1));
+        assert_ne!(a.position_hash(), b.position_hash());
     }
 }
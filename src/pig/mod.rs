@@ -0,0 +1,259 @@
+/*
+    Implementation of the dice game Pig
+
+    On your turn you roll a die as many times as you like, adding each roll
+    to your turn total, until you either hold (adding the turn total to your
+    score) or roll a 1 (losing the turn total). The first player to reach
+    the target score wins.
+
+    Each roll of the die is a chance node.
+*/
+
+use crate::abstract_game::{AbstractGame, GameStatus};
+use crate::console::Console;
+use crate::player::Player;
+use crate::random::GameRng;
+use crate::util;
+
+use rand::Rng;
+use std::fmt::{self, Display};
+
+const DIE_SIDES: usize = 6;
+
+#[derive(Debug, Clone)]
+pub struct PigState<const N: usize> {
+    target: usize,
+    scores: [usize; N],
+    turn_total: usize,
+    // Whether the die has been rolled and is waiting to land
+    rolling: bool,
+    to_move: Player<N>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PigMove {
+    Roll,
+    Hold,
+    // Move of nature: the number the die lands on
+    Die(usize),
+}
+impl Display for PigMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PigMove::Roll => write!(f, "Roll"),
+            PigMove::Hold => write!(f, "Hold"),
+            PigMove::Die(n) => write!(f, "Rolled {}", n),
+        }
+    }
+}
+
+impl<const N: usize> PigState<N> {
+    fn winner(&self) -> Option<Player<N>> {
+        Player::all().find(|p| self.scores[p.as_index()] >= self.target)
+    }
+}
+
+impl<const N: usize> AbstractGame<N> for PigState<N> {
+    const NAME: &'static str = "pig";
+
+    type Move = PigMove;
+    type Observation = Self;
+    type SetupParams = usize;
+
+    fn setup_from_user_input(console: &mut dyn Console) -> usize {
+        util::from_user_input_satisfying(
+            console,
+            "Target score? ",
+            "Type a positive integer. ",
+            "Type a positive integer. ",
+            |&target| target > 0,
+        )
+    }
+
+    fn format_params(target: &usize) -> String {
+        target.to_string()
+    }
+
+    fn parse_params(raw: &str) -> Result<usize, String> {
+        let target: usize = raw
+            .trim()
+            .parse()
+            .map_err(|_| "Target score should be an integer. ".to_string())?;
+        if target == 0 {
+            return Err("Target score should be positive. ".to_string());
+        }
+        Ok(target)
+    }
+
+    fn game_setup(target: usize, rng: &mut GameRng) -> Self {
+        // The first player is chosen at random
+        let to_move = Player::from_index(rng.gen_range(0..N)).unwrap();
+        Self { target, scores: [0; N], turn_total: 0, rolling: false, to_move }
+    }
+
+    fn status(&self) -> GameStatus<N> {
+        if let Some(plyr) = self.winner() {
+            GameStatus::Won(plyr)
+        } else if self.rolling {
+            GameStatus::Chance
+        } else {
+            GameStatus::ToMove(self.to_move)
+        }
+    }
+
+    fn chance_outcomes(&self) -> Vec<(PigMove, f64)> {
+        debug_assert!(self.rolling);
+        let prob = 1.0 / DIE_SIDES as f64;
+        (1..=DIE_SIDES).map(|n| (PigMove::Die(n), prob)).collect()
+    }
+
+    fn query(&self) -> String {
+        "Roll or hold? ".to_string()
+    }
+
+    fn parse_move(&self, raw: &str) -> Result<PigMove, String> {
+        let raw = raw.trim().to_lowercase();
+        match raw.as_str() {
+            "roll" | "r" => Ok(PigMove::Roll),
+            "hold" | "h" => Ok(PigMove::Hold),
+            _ => raw
+                .trim_start_matches("rolled")
+                .trim()
+                .parse()
+                .map(PigMove::Die)
+                .map_err(|_| "Type 'roll' or 'hold'. ".to_string()),
+        }
+    }
+
+    fn check_move(&self, mv: &PigMove) -> Result<(), String> {
+        match (self.status(), *mv) {
+            (GameStatus::Chance, PigMove::Die(n))
+                if (1..=DIE_SIDES).contains(&n) =>
+            {
+                Ok(())
+            }
+            (GameStatus::Chance, _) => {
+                Err("Wait for the die to land. ".to_string())
+            }
+            (GameStatus::ToMove(_), PigMove::Roll) => Ok(()),
+            (GameStatus::ToMove(_), PigMove::Hold) if self.turn_total == 0 => {
+                Err("Roll at least once before holding. ".to_string())
+            }
+            (GameStatus::ToMove(_), PigMove::Hold) => Ok(()),
+            (GameStatus::ToMove(_), PigMove::Die(_)) => {
+                Err("You can't choose what the die lands on! ".to_string())
+            }
+            _ => Err("The game is over. ".to_string()),
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<PigMove> {
+        match self.status() {
+            GameStatus::Chance => (1..=DIE_SIDES).map(PigMove::Die).collect(),
+            GameStatus::ToMove(_) if self.turn_total == 0 => {
+                vec![PigMove::Roll]
+            }
+            GameStatus::ToMove(_) => vec![PigMove::Roll, PigMove::Hold],
+            _ => Vec::new(),
+        }
+    }
+
+    fn make_move(&mut self, mv: PigMove) {
+        debug_assert!(self.is_valid_move(&mv));
+        match mv {
+            PigMove::Roll => self.rolling = true,
+            PigMove::Die(1) => {
+                self.rolling = false;
+                self.turn_total = 0;
+                self.to_move.advance_player();
+            }
+            PigMove::Die(n) => {
+                self.rolling = false;
+                self.turn_total += n;
+            }
+            PigMove::Hold => {
+                self.scores[self.to_move.as_index()] += self.turn_total;
+                self.turn_total = 0;
+                if self.winner().is_none() {
+                    self.to_move.advance_player();
+                }
+            }
+        }
+    }
+
+    fn print_state_visible(&self, _plyr: Player<N>) -> String {
+        let scores: Vec<String> = Player::<N>::all()
+            .map(|p| format!("{}: {}", p, self.scores[p.as_index()]))
+            .collect();
+        format!(
+            "Scores: {} (target {})\nTurn total: {}",
+            scores.join(", "),
+            self.target,
+            self.turn_total
+        )
+    }
+
+    fn observe(&self, _plyr: Player<N>) -> Self {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::TwoPlayers;
+    use crate::random;
+
+    fn new_game(target: usize) -> PigState<2> {
+        let mut game = PigState::game_setup(target, &mut random::seeded(0));
+        game.to_move = TwoPlayers::ONE;
+        game
+    }
+
+    #[test]
+    fn test_chance_node() {
+        let mut game = new_game(10);
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::ONE));
+        assert_eq!(game.legal_moves(), vec![PigMove::Roll]);
+        game.make_move(PigMove::Roll);
+        assert_eq!(game.status(), GameStatus::Chance);
+        let outcomes = game.chance_outcomes();
+        let total: f64 = outcomes.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        for (mv, _) in &outcomes {
+            assert!(game.legal_moves_agree(mv));
+        }
+        assert!(!game.is_valid_move(&PigMove::Hold));
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut game = new_game(10);
+        game.make_move(PigMove::Roll);
+        game.make_move(PigMove::Die(1));
+        assert_eq!(game.status(), GameStatus::ToMove(TwoPlayers::TWO));
+        game.make_move(PigMove::Roll);
+        game.make_move(PigMove::Die(6));
+        game.make_move(PigMove::Roll);
+        game.make_move(PigMove::Die(4));
+        assert_eq!(game.turn_total, 10);
+        game.make_move(PigMove::Hold);
+        assert_eq!(game.status(), GameStatus::Won(TwoPlayers::TWO));
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(PigState::<2>::parse_params(" 100 "), Ok(100));
+        assert!(PigState::<2>::parse_params("0").is_err());
+        assert!(PigState::<2>::parse_params("lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_move_round_trip() {
+        let game = new_game(10);
+        for &mv in &[PigMove::Roll, PigMove::Hold, PigMove::Die(3)] {
+            assert_eq!(game.parse_move(&mv.to_string()), Ok(mv));
+        }
+    }
+}
@@ -2,86 +2,510 @@
     Code to play (execute) a game
 */
 
-use super::abstract_game::{AbstractGame, Ai, GameStatus};
+use super::abstract_game::{AbstractGame, Ai, GameStatus, Turn};
+use super::console::Console;
+use super::history::History;
 use super::player::Player;
+use super::random::{self, GameRng};
+use super::record::{GameRecord, PlayerKind};
 use super::util;
 
 use std::fmt::Display;
 
+// Itertools for .sorted() and .join()
+use itertools::Itertools;
+
 /*
     Code to play (execute) a game
 */
 
+/// What the user can type on their turn: a move, or a command to go
+/// back and forth through the game history, or to save or load the game
+enum Command<M> {
+    Move(M),
+    Undo,
+    Redo,
+    Save(String),
+    Load(String),
+}
+
+const COMMANDS_HELP: &str = "(Type 'undo' to take back a move, 'redo' to \
+    replay it, 'save <file>' to save the game, or 'load <file>' to resume a \
+    saved game.)";
+
+/// Get a valid move or command from the user, playing as plyr
+fn query_command<G, const N: usize>(
+    console: &mut dyn Console,
+    game: &G,
+    plyr: Player<N>,
+) -> Command<G::Move>
+where
+    G: AbstractGame<N>,
+{
+    let query = game.query();
+    let cmd = util::from_user_input_parsing(console, &query, |raw| {
+        match raw.trim().split_once(' ') {
+            Some(("save", path)) => Ok(Command::Save(path.trim().to_string())),
+            Some(("load", path)) => Ok(Command::Load(path.trim().to_string())),
+            _ => match raw.trim() {
+                "undo" => Ok(Command::Undo),
+                "redo" => Ok(Command::Redo),
+                _ => game.parse_valid_move_for(plyr, &raw).map(Command::Move),
+            },
+        }
+    });
+    if let Command::Move(mv) = &cmd {
+        debug_assert!(game.check_move_for(plyr, mv).is_ok());
+        debug_assert!(game.legal_moves_for(plyr).contains(mv));
+        debug_assert!(
+            game.cur_player().is_none() || game.legal_moves_agree(mv)
+        );
+    }
+    cmd
+}
+
+/// In hot-seat play, scroll a simultaneous move off the screen so that the
+/// next player doesn't see it
+fn hide_move(console: &mut dyn Console) {
+    console.println(&"\n".repeat(50));
+    console.println("Move recorded. Pass to the next player.");
+}
+
+/// Describe the outcome of an ended game
+fn outcome<const N: usize>(status: GameStatus<N>) -> String {
+    match status {
+        GameStatus::ToMove(_)
+        | GameStatus::Simultaneous(_)
+        | GameStatus::Chance => unreachable!(),
+        GameStatus::Won(plyr) => format!("{} wins!", plyr),
+        GameStatus::Draw => "It's a draw!".to_string(),
+        GameStatus::SharedWin(plyrs) => format!("{} win!", plyrs),
+        GameStatus::Ranked(places) => Player::<N>::all()
+            .map(|plyr| (places[plyr.as_index()], plyr))
+            .sorted()
+            .map(|(place, plyr)| {
+                format!("{}: {}", util::ordinal(place + 1), plyr)
+            })
+            .join("\n"),
+    }
+}
+
+/// Describe the outcome of an ended game from your point of view
+fn outcome_for<const N: usize>(
+    status: GameStatus<N>,
+    you: Player<N>,
+) -> String {
+    match status {
+        GameStatus::ToMove(_)
+        | GameStatus::Simultaneous(_)
+        | GameStatus::Chance => unreachable!(),
+        GameStatus::Won(plyr) if plyr == you => "You win!".to_string(),
+        GameStatus::Won(plyr) => format!("You lose! {} wins.", plyr),
+        GameStatus::Draw => "It's a draw!".to_string(),
+        GameStatus::SharedWin(plyrs) if plyrs.contains(you) => {
+            format!("You win! ({} win together.)", plyrs)
+        }
+        GameStatus::SharedWin(plyrs) => format!("You lose! {} win.", plyrs),
+        GameStatus::Ranked(places) => format!(
+            "You finished {}.\n{}",
+            util::ordinal(places[you.as_index()] + 1),
+            outcome(status)
+        ),
+    }
+}
+
+/// Final scores of an ended game, one line per player
+fn final_scores<G, const N: usize>(game: &G) -> String
+where
+    G: AbstractGame<N>,
+{
+    let payoffs = game.payoffs().expect("game should be ended");
+    let mut scores = Player::<N>::all().map(|plyr| {
+        let score = payoffs[plyr.as_index()];
+        if score.fract() == 0.0 {
+            format!("{}: {}", plyr, score)
+        } else {
+            format!("{}: {:.3}", plyr, score)
+        }
+    });
+    format!("Final scores:\n{}", scores.join("\n"))
+}
+
+/// Random seed for a game: the first command-line argument if there is one
+/// (so that a game can be replayed), or else a fresh random seed
+pub fn game_seed(console: &mut dyn Console) -> u64 {
+    let seed = match std::env::args().nth(1) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            console.println(&format!(
+                "Ignoring seed '{}': it should be a nonnegative integer.",
+                arg
+            ));
+            random::random_seed()
+        }),
+        None => random::random_seed(),
+    };
+    console.println(&format!("Random seed: {}", seed));
+    seed
+}
+
 /// Execute the game where you play the move for every player
 /// N is the number of players
-pub fn play_vs_yourself<G, const N: usize>()
+/// In simultaneous-move positions, each player's move is hidden before
+/// the next player chooses.
+/// All randomness comes from the given seed.
+pub fn play_vs_yourself<G, const N: usize>(console: &mut dyn Console, seed: u64)
 where
-    G: AbstractGame<N>,
-    G::Move: Display,
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
 {
-    let mut game = G::new_from_user_input();
+    let mut rng = random::seeded(seed);
+    let params = G::setup_from_user_input(console);
+    let players = vec![PlayerKind::Human; N];
+    let mut session = Session::<G, N>::new(params, seed, players, &mut rng);
+    console.println(COMMANDS_HELP);
     loop {
+        let game = session.history.game();
         match game.status() {
             GameStatus::ToMove(plyr) => {
-                println!("===== {}'s turn =====", plyr);
-                println!("{}", game.print_state_visible(plyr));
+                console.println(&format!("===== {}'s turn =====", plyr));
+                console.println(&game.print_state_visible(plyr));
 
-                let query = game.query();
-                let mv = util::from_user_input_parsing(&query, |raw| {
-                    game.parse_valid_move(&raw)
-                });
-                debug_assert!(game.is_valid_move(&mv));
+                match query_command(console, game, plyr) {
+                    Command::Move(mv) => {
+                        console.println(&format!("Move chosen: {}", mv));
+                        session.history.make_move(mv);
+                    }
+                    cmd => session.run_command(console, cmd, None),
+                }
+            }
+            GameStatus::Simultaneous(plyrs) => {
+                let mut moves = Vec::new();
+                let mut command = None;
+                for plyr in plyrs.iter() {
+                    console.println(&format!("===== {}'s turn =====", plyr));
+                    console.println(&game.print_state_visible(plyr));
 
-                println!("Move chosen: {}", mv);
-                game.make_move(mv);
+                    match query_command(console, game, plyr) {
+                        Command::Move(mv) => {
+                            moves.push((plyr, mv));
+                            if moves.len() < plyrs.len() {
+                                hide_move(console);
+                            }
+                        }
+                        cmd => {
+                            command = Some(cmd);
+                            break;
+                        }
+                    }
+                }
+                match command {
+                    None => {
+                        let turn = Turn::Simultaneous(moves);
+                        console.println(&format!("Moves chosen: {}", turn));
+                        session.history.make_turn(turn);
+                    }
+                    Some(cmd) => session.run_command(console, cmd, None),
+                }
             }
-            GameStatus::Won(plyr) => {
-                println!("{} wins!", plyr);
+            GameStatus::Chance => {
+                let turn = make_chance_turn(&mut session.history, &mut rng);
+                console.println(&format!("Chance: {}", turn));
+            }
+            status => {
+                console.println(&outcome(status));
+                console.println(&final_scores(game));
+                session.offer_save(console);
                 return;
             }
         }
     }
 }
 
-/// Play the game using AIs for all the other players
-/// N is the number of players and 'you' is your player
-pub fn play_vs_ai<G, A, const N: usize>(you: Player<N>)
+/// Whether it is your turn (possibly along with others); for `you` = None,
+/// whether it is any player's turn (not a chance node or the end)
+fn is_turn_of<G, const N: usize>(game: &G, you: Option<Player<N>>) -> bool
+where
+    G: AbstractGame<N>,
+{
+    match (game.status(), you) {
+        (status, Some(you)) => status.is_to_move(you),
+        (GameStatus::ToMove(_), None) => true,
+        (GameStatus::Simultaneous(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Make the chance move at a chance node, returning it. A chance move that
+/// was taken back is made again (so that taking back a roll of the dice
+/// and rolling again can't change the result); otherwise one is drawn.
+fn make_chance_turn<G, const N: usize>(
+    history: &mut History<G, N>,
+    rng: &mut GameRng,
+) -> Turn<G::Move, N>
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone,
+{
+    history.redo().unwrap_or_else(|| {
+        let mv = history.game().sample_chance_move(rng);
+        history.make_move(mv.clone());
+        Turn::Move(mv)
+    })
+}
+
+/// Take back turns until it is your turn again (at least one turn).
+/// Returns whether any turns were taken back.
+fn undo_to_turn<G, const N: usize>(
+    console: &mut dyn Console,
+    history: &mut History<G, N>,
+    you: Option<Player<N>>,
+) -> bool
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone + Display,
+{
+    let mut undone = false;
+    while let Some(turn) = history.undo() {
+        console.println(&format!("Took back: {}", turn));
+        undone = true;
+        if is_turn_of(history.game(), you) {
+            break;
+        }
+    }
+    undone
+}
+
+/// Replay turns until it is your turn again (at least one turn).
+/// Returns whether any turns were replayed.
+fn redo_to_turn<G, const N: usize>(
+    console: &mut dyn Console,
+    history: &mut History<G, N>,
+    you: Option<Player<N>>,
+) -> bool
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone + Display,
+{
+    let mut redone = false;
+    while let Some(turn) = history.redo() {
+        console.println(&format!("Replayed: {}", turn));
+        redone = true;
+        if is_turn_of(history.game(), you) {
+            break;
+        }
+    }
+    redone
+}
+
+/// A game in progress: its history, and the setup needed to save it
+struct Session<G, const N: usize>
+where
+    G: AbstractGame<N> + Clone,
+    G::Move: Clone,
+{
+    params: G::SetupParams,
+    seed: u64,
+    players: Vec<PlayerKind>,
+    history: History<G, N>,
+}
+
+impl<G, const N: usize> Session<G, N>
+where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
+{
+    /// Set up a new game; the seed should be the one rng was seeded with
+    fn new(
+        params: G::SetupParams,
+        seed: u64,
+        players: Vec<PlayerKind>,
+        rng: &mut GameRng,
+    ) -> Self {
+        let history = History::new(G::game_setup(params.clone(), rng));
+        Self { params, seed, players, history }
+    }
+
+    fn record(&self) -> GameRecord<G, N> {
+        GameRecord::new(&self.params, self.seed, &self.players, &self.history)
+    }
+
+    /// At the end of the game, offer to save a record of it
+    fn offer_save(&self, console: &mut dyn Console) {
+        let path = util::user_input(
+            console,
+            "Type a file name to save a record of the game, \
+            or press enter to skip: ",
+        );
+        let path = path.trim();
+        if !path.is_empty() {
+            match self.record().save(path) {
+                Ok(()) => {
+                    console.println(&format!("Record saved to {}.", path))
+                }
+                Err(msg) => console.println(&msg),
+            }
+        }
+    }
+
+    /// Carry out a command other than a move
+    fn run_command(
+        &mut self,
+        console: &mut dyn Console,
+        cmd: Command<G::Move>,
+        you: Option<Player<N>>,
+    ) {
+        match cmd {
+            Command::Undo => {
+                if !undo_to_turn(console, &mut self.history, you) {
+                    console.println("No moves to take back.");
+                }
+            }
+            Command::Redo => {
+                if !redo_to_turn(console, &mut self.history, you) {
+                    console.println("No moves to replay.");
+                }
+            }
+            Command::Save(path) => match self.record().save(&path) {
+                Ok(()) => console.println(&format!("Game saved to {}.", path)),
+                Err(msg) => console.println(&msg),
+            },
+            Command::Load(path) => match GameRecord::<G, N>::load(&path) {
+                Ok(record) => {
+                    self.history = record.history();
+                    self.params = record.params;
+                    self.seed = record.seed;
+                    self.players = record.players;
+                    console.println(&format!("Game loaded from {}.", path));
+                }
+                Err(msg) => console.println(&msg),
+            },
+            Command::Move(_) => unreachable!(),
+        }
+    }
+}
+
+/// Name of an AI for game records: its type name, without the module path
+/// or generic parameters
+fn ai_name<A>() -> String {
+    let name = std::any::type_name::<A>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name).to_string()
+}
+
+/// Ask the AI for plyr's move, giving it only plyr's observation
+fn ai_move<G, A, const N: usize>(
+    ai: &mut A,
+    game: &G,
+    plyr: Player<N>,
+    rng: &mut GameRng,
+) -> G::Move
 where
     G: AbstractGame<N>,
-    G::Move: Display,
     A: Ai<G, N>,
 {
-    let mut game = G::new_from_user_input();
+    let legal_moves = game.legal_moves_for(plyr);
+    let mv = ai.ai_move(&game.observe(plyr), plyr, &legal_moves, rng);
+    debug_assert!(game.check_move_for(plyr, &mv).is_ok());
+    mv
+}
+
+/// Play the game using AIs for all the other players
+/// N is the number of players and 'you' is your player
+/// Undo and redo take back (or replay) the computer's moves along with yours.
+/// In simultaneous-move positions, the computers choose without seeing
+/// your move, and all moves are revealed together.
+/// All randomness comes from the given seed.
+pub fn play_vs_ai<G, A, const N: usize>(
+    console: &mut dyn Console,
+    seed: u64,
+    you: Player<N>,
+) where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
+    A: Ai<G, N>,
+{
+    let mut rng = random::seeded(seed);
+    let params = G::setup_from_user_input(console);
+    let players = Player::<N>::all()
+        .map(|plyr| {
+            if plyr == you {
+                PlayerKind::Human
+            } else {
+                PlayerKind::Computer(ai_name::<A>())
+            }
+        })
+        .collect();
+    let mut session = Session::<G, N>::new(params, seed, players, &mut rng);
     let mut ai = A::new();
+    console.println(COMMANDS_HELP);
     loop {
+        let game = session.history.game();
         match game.status() {
-            GameStatus::ToMove(plyr) => {
-                if plyr == you {
-                    println!("===== Your turn =====");
-                    println!("{}", game.print_state_visible(you));
-
-                    let query = game.query();
-                    let mv = util::from_user_input_parsing(&query, |raw| {
-                        game.parse_valid_move(&raw)
-                    });
-                    debug_assert!(game.is_valid_move(&mv));
-
-                    println!("Your move: {}", mv);
-                    game.make_move(mv);
-                } else {
-                    println!("===== Computer {}'s turn =====", plyr);
-                    let mv = ai.ai_move(&game, plyr);
-                    debug_assert!(game.is_valid_move(&mv));
-                    println!("Computer {}'s move: {}", plyr, mv);
-                    game.make_move(mv);
+            GameStatus::ToMove(plyr) if plyr == you => {
+                console.println("===== Your turn =====");
+                console.println(&game.print_state_visible(you));
+
+                match query_command(console, game, you) {
+                    Command::Move(mv) => {
+                        console.println(&format!("Your move: {}", mv));
+                        session.history.make_move(mv);
+                    }
+                    cmd => session.run_command(console, cmd, Some(you)),
                 }
             }
-            GameStatus::Won(plyr) => {
-                if plyr == you {
-                    println!("You win!");
-                } else {
-                    println!("You lose! Player {} wins.", plyr);
+            GameStatus::ToMove(plyr) => {
+                console
+                    .println(&format!("===== Computer {}'s turn =====", plyr));
+                let mv = ai_move(&mut ai, game, plyr, &mut rng);
+                debug_assert!(game.is_valid_move(&mv));
+                debug_assert!(game.legal_moves_agree(&mv));
+                console.println(&format!("Computer {}'s move: {}", plyr, mv));
+                session.history.make_move(mv);
+            }
+            GameStatus::Simultaneous(plyrs) => {
+                let mut moves = Vec::new();
+                let mut command = None;
+                for plyr in plyrs.iter() {
+                    if plyr == you {
+                        console.println("===== Your turn =====");
+                        console.println(&game.print_state_visible(you));
+
+                        match query_command(console, game, you) {
+                            Command::Move(mv) => moves.push((you, mv)),
+                            cmd => {
+                                command = Some(cmd);
+                                break;
+                            }
+                        }
+                    } else {
+                        let mv = ai_move(&mut ai, game, plyr, &mut rng);
+                        console.println(&format!(
+                            "Computer {} has chosen a move.",
+                            plyr
+                        ));
+                        moves.push((plyr, mv));
+                    }
+                }
+                match command {
+                    None => {
+                        let turn = Turn::Simultaneous(moves);
+                        console.println(&format!("Moves chosen: {}", turn));
+                        session.history.make_turn(turn);
+                    }
+                    Some(cmd) => session.run_command(console, cmd, Some(you)),
                 }
+            }
+            GameStatus::Chance => {
+                let turn = make_chance_turn(&mut session.history, &mut rng);
+                console.println(&format!("Chance: {}", turn));
+            }
+            status => {
+                console.println(&outcome_for(status, you));
+                console.println(&final_scores(game));
+                session.offer_save(console);
                 return;
             }
         }
@@ -91,27 +515,259 @@ where
 /// Play the game vs AIs, where you are player 1
 /// Panics if N = 0, but N = 0 should not really be possible (see
 /// comment in abstract_game.rs)
-pub fn play_vs_ai_as_p1<G, A, const N: usize>()
-where
-    G: AbstractGame<N>,
-    G::Move: Display,
+pub fn play_vs_ai_as_p1<G, A, const N: usize>(
+    console: &mut dyn Console,
+    seed: u64,
+) where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
     A: Ai<G, N>,
 {
     play_vs_ai::<G, A, N>(
+        console,
+        seed,
         Player::from_index(0)
             .expect("Error: tried to play a game with 0 players"),
     );
 }
 
 // /// Play the game vs AIs, where you choose what player to play
-pub fn play_vs_ai_choose_player<G, A, const N: usize>()
-where
-    G: AbstractGame<N>,
-    G::Move: Display,
+pub fn play_vs_ai_choose_player<G, A, const N: usize>(
+    console: &mut dyn Console,
+    seed: u64,
+) where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
     A: Ai<G, N>,
 {
     let query = format!("Choose a player between 1 and {}: ", N);
     let requery = format!("Not between 1 and {}. Try again: ", N);
-    let player = util::from_user_input(&query, &requery);
-    play_vs_ai::<G, A, N>(player);
+    let player = util::from_user_input(console, &query, &requery);
+    play_vs_ai::<G, A, N>(console, seed, player);
+}
+
+/// Ask whether to play against AIs (choosing your player) or for every
+/// player yourself, and play the game
+pub fn play_vs_ai_or_yourself<G, A, const N: usize>(
+    console: &mut dyn Console,
+    seed: u64,
+) where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
+    A: Ai<G, N>,
+{
+    let vs_computer = util::from_user_input_parsing(
+        console,
+        "Play against the computer? (y/n) ",
+        |raw| match raw.trim() {
+            "y" => Ok(true),
+            "n" => Ok(false),
+            _ => Err("Type 'y' or 'n'. ".to_string()),
+        },
+    );
+    if vs_computer {
+        play_vs_ai_choose_player::<G, A, N>(console, seed);
+    } else {
+        play_vs_yourself::<G, N>(console, seed);
+    }
+}
+
+const REPLAY_HELP: &str = "(Press enter or type 'n' for the next turn, 'p' \
+    for the previous turn, 's' or 'e' to go to the start or end, 'view <k>' \
+    to see the game as player k sees it, 'view all' to see everything, or \
+    'q' to quit.)";
+
+/// Which player's view of the game to show when replaying
+#[derive(Clone, Copy)]
+enum View<const N: usize> {
+    Player(Player<N>),
+    All,
+}
+
+/// Print the game as seen from a view
+fn print_view<G, const N: usize>(
+    console: &mut dyn Console,
+    game: &G,
+    view: View<N>,
+) where
+    G: AbstractGame<N>,
+{
+    match view {
+        View::Player(plyr) => console.println(&game.print_state_visible(plyr)),
+        View::All => {
+            for plyr in Player::<N>::all() {
+                console.println(&format!("--- As seen by {} ---", plyr));
+                console.println(&game.print_state_visible(plyr));
+            }
+        }
+    }
+}
+
+/// Step forward and backward through a recorded game
+pub fn replay<G, const N: usize>(
+    console: &mut dyn Console,
+    record: &GameRecord<G, N>,
+) where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
+{
+    let total = record.turns.len();
+    let mut history = record.history();
+    while history.undo().is_some() {}
+    let mut turn = 0;
+    let mut view = View::All;
+    for (plyr, kind) in Player::<N>::all().zip(&record.players) {
+        console.println(&format!("{}: {}", plyr, kind));
+    }
+    console.println(REPLAY_HELP);
+    loop {
+        if turn == 0 {
+            console.println(&format!(
+                "===== Start of the game ({} turns) =====",
+                total
+            ));
+        } else {
+            let last = history.turns().last().expect("a turn was made");
+            console.println(&format!(
+                "===== After turn {} of {}: {} =====",
+                turn, total, last
+            ));
+        }
+        print_view(console, history.game(), view);
+        let status = history.game().status();
+        if status.is_ended() {
+            console.println(&outcome(status));
+        }
+
+        let raw = util::user_input(console, "> ");
+        match raw.trim() {
+            "" | "n" => match history.redo() {
+                Some(_) => turn += 1,
+                None => console.println("This is the end of the record."),
+            },
+            "p" => match history.undo() {
+                Some(_) => turn -= 1,
+                None => console.println("This is the start of the game."),
+            },
+            "s" => {
+                while history.undo().is_some() {}
+                turn = 0;
+            }
+            "e" => {
+                while history.redo().is_some() {}
+                turn = total;
+            }
+            "q" => return,
+            "view all" => view = View::All,
+            cmd => match cmd.strip_prefix("view ").map(|k| k.trim().parse()) {
+                Some(Ok(plyr)) => view = View::Player(plyr),
+                _ => console.println(REPLAY_HELP),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::Scripted;
+    use crate::nim::NimState;
+    use crate::pig::PigState;
+    use crate::rps::RpsState;
+
+    /// AI that always plays the first legal move
+    struct FirstMoveAi;
+
+    impl<G, const N: usize> Ai<G, N> for FirstMoveAi
+    where
+        G: AbstractGame<N>,
+        G::Move: Clone,
+    {
+        fn new() -> Self {
+            Self
+        }
+        fn ai_move(
+            &mut self,
+            _obs: &G::Observation,
+            _plyr: Player<N>,
+            legal_moves: &[G::Move],
+            _rng: &mut GameRng,
+        ) -> G::Move {
+            legal_moves[0].clone()
+        }
+    }
+
+    #[test]
+    fn test_play_vs_yourself() {
+        let mut console = Scripted::new(&[
+            "2", "1", "2", "", "1 1", "undo", "redo", "2 3", "2 2", "",
+        ]);
+        play_vs_yourself::<NimState<2>, 2>(&mut console, 0);
+        assert!(console.is_finished());
+        let output = console.output();
+        assert!(output.contains("Took back: Take 1 from pile 1"));
+        assert!(output.contains("Replayed: Take 1 from pile 1"));
+        assert!(output.contains("Move chosen: Take 2 from pile 2"));
+        assert!(output.contains("Player 2 wins!"));
+    }
+
+    #[test]
+    fn test_play_vs_ai() {
+        let mut console = Scripted::new(&["1", "3", "", "1 1", "1 1", ""]);
+        play_vs_ai::<NimState<2>, FirstMoveAi, 2>(
+            &mut console,
+            0,
+            Player::from_index(0).unwrap(),
+        );
+        assert!(console.is_finished());
+        let output = console.output();
+        assert!(output.contains("Computer Player 2's move: Take 1 from pile 1"));
+        assert!(output.contains("You win!"));
+    }
+
+    #[test]
+    fn test_simultaneous_moves() {
+        let mut console = Scripted::new(&["1", "rock", "paper", ""]);
+        play_vs_yourself::<RpsState, 2>(&mut console, 0);
+        assert!(console.is_finished());
+        let output = console.output();
+        assert!(output.contains("Move recorded. Pass to the next player."));
+        assert!(output.contains("Player 1: rock; Player 2: paper"));
+        assert!(output.contains("Player 2 wins!"));
+    }
+
+    #[test]
+    fn test_undo_chance() {
+        let mut console = Scripted::new(&[
+            "1", "roll", "undo", "roll", "undo", "roll", "hold", "",
+        ]);
+        play_vs_yourself::<PigState<2>, 2>(&mut console, 0);
+        assert!(console.is_finished());
+        let output = console.output();
+        assert!(output.contains("Took back: Roll"));
+        // Rolling again after taking back a roll gets the same number
+        let rolls: Vec<&str> =
+            output.lines().filter(|l| l.starts_with("Chance: ")).collect();
+        assert_eq!(rolls.len(), 3);
+        assert!(rolls.iter().all(|&roll| roll == rolls[0]));
+    }
+
+    #[test]
+    fn test_replay() {
+        let raw = "game: nim\nseed: 0\nparams: 2 1\nturns:\n1 2\n2 1\n";
+        let record = GameRecord::<NimState<2>, 2>::from_text(raw).unwrap();
+        let mut console =
+            Scripted::new(&["", "view 2", "e", "n", "p", "s", "p", "q"]);
+        replay(&mut console, &record);
+        assert!(console.is_finished());
+        let output = console.output();
+        assert!(output.contains("After turn 1 of 2: Take 2 from pile 1"));
+        assert!(output.contains("Player 2 wins!"));
+        assert!(output.contains("This is the end of the record."));
+        assert!(output.contains("This is the start of the game."));
+    }
 }
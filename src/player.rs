@@ -3,13 +3,14 @@
 */
 
 use std::fmt::{self, Display};
+use std::iter::FromIterator;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
 /// Struct representing a player (player 0, player 1, etc.)
 /// (not with respect to any specific game)
 /// N is the number of players
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Player<const N: usize>(usize);
 impl<const N: usize> Player<N> {
     /// Internal: invariant check
@@ -34,6 +35,11 @@ impl<const N: usize> Player<N> {
         self.0
     }
 
+    /// All players, in order
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..N).map(Self)
+    }
+
     /// Cycle between players
     pub fn prev_player(&self) -> Self {
         debug_assert!(self.is_valid());
@@ -69,6 +75,62 @@ impl<const N: usize> Display for Player<N> {
     }
 }
 
+/// Set of players (e.g. the players sharing a win)
+/// N is the number of players
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PlayerSet<const N: usize>([bool; N]);
+impl<const N: usize> PlayerSet<N> {
+    pub fn empty() -> Self {
+        Self([false; N])
+    }
+    pub fn all() -> Self {
+        Self([true; N])
+    }
+
+    pub fn insert(&mut self, plyr: Player<N>) {
+        self.0[plyr.as_index()] = true;
+    }
+    pub fn remove(&mut self, plyr: Player<N>) {
+        self.0[plyr.as_index()] = false;
+    }
+    pub fn contains(&self, plyr: Player<N>) -> bool {
+        self.0[plyr.as_index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().filter(|&&b| b).count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Players in the set, in order
+    pub fn iter(&self) -> impl Iterator<Item = Player<N>> + '_ {
+        Player::all().filter(move |&plyr| self.contains(plyr))
+    }
+}
+
+impl<const N: usize> FromIterator<Player<N>> for PlayerSet<N> {
+    fn from_iter<I: IntoIterator<Item = Player<N>>>(iter: I) -> Self {
+        let mut result = Self::empty();
+        for plyr in iter {
+            result.insert(plyr);
+        }
+        result
+    }
+}
+
+impl<const N: usize> Display for PlayerSet<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.iter().map(|p| p.name_upper()).collect();
+        match names.split_last() {
+            None => write!(f, "nobody"),
+            Some((last, [])) => write!(f, "{}", last),
+            Some((last, rest)) => write!(f, "{} and {}", rest.join(", "), last),
+        }
+    }
+}
+
 /// Custom error struct for parsing player from string
 #[derive(Debug)]
 pub enum ParsePlayerErr {
@@ -182,6 +244,32 @@ mod tests {
     }
 
     #[test]
+    fn test_all() {
+        assert_eq!(TwoPlayers::all().collect::<Vec<_>>(), vec![ONE, TWO]);
+    }
+
+    #[test]
+    fn test_player_set() {
+        let mut set = PlayerSet::<3>::empty();
+        assert!(set.is_empty());
+        assert_eq!(&set.to_string(), "nobody");
+        let p2 = Player::<3>::from_index(1).unwrap();
+        set.insert(p2);
+        assert!(set.contains(p2));
+        assert_eq!(set.len(), 1);
+        assert_eq!(&set.to_string(), "Player 2");
+        assert_eq!(
+            &PlayerSet::<3>::all().to_string(),
+            "Player 1, Player 2 and Player 3"
+        );
+        set.remove(p2);
+        assert_eq!(set, PlayerSet::empty());
+        let both: PlayerSet<2> = vec![TWO, ONE].into_iter().collect();
+        assert_eq!(both, PlayerSet::all());
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_to_from_bool() {
         assert_eq!(ONE.as_bool(), false);
         assert_eq!(TwoPlayers::from_bool(false), ONE);
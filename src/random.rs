@@ -0,0 +1,57 @@
+/*
+    Randomness
+
+    All randomness in a game (random setup, chance moves, and the choices
+    of AIs) is drawn from a single seeded random number generator, so that
+    a game can be reproduced from its seed.
+*/
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Random number generator used throughout the crate
+pub type GameRng = StdRng;
+
+/// Generator from a seed: the same seed gives the same game
+pub fn seeded(seed: u64) -> GameRng {
+    GameRng::seed_from_u64(seed)
+}
+
+/// A fresh seed, for when the user doesn't choose one
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+/// Sample an outcome from a distribution given as (outcome, probability)
+/// pairs. Probabilities need not be normalized, but should be nonnegative
+/// and not all zero.
+pub fn sample<'a, T>(dist: &'a [(T, f64)], rng: &mut GameRng) -> &'a T {
+    let weights = WeightedIndex::new(dist.iter().map(|(_, p)| *p))
+        .expect("invalid probability distribution");
+    &dist[weights.sample(rng)].0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_reproducible() {
+        let dist = [(1, 0.2), (2, 0.3), (3, 0.5)];
+        let mut rng1 = seeded(7);
+        let mut rng2 = seeded(7);
+        for _ in 0..20 {
+            assert_eq!(sample(&dist, &mut rng1), sample(&dist, &mut rng2));
+        }
+    }
+
+    #[test]
+    fn test_sample_support() {
+        let dist = [('a', 0.0), ('b', 1.0), ('c', 0.0)];
+        let mut rng = seeded(0);
+        for _ in 0..20 {
+            assert_eq!(sample(&dist, &mut rng), &'b');
+        }
+    }
+}
@@ -0,0 +1,367 @@
+/*
+    Records of games, for saving, loading and replaying
+
+    A record holds what is needed to recreate a game: the setup parameters,
+    the random seed used for setup, who played, and the turns made so far.
+    As text, it looks like:
+
+        game: nim
+        seed: 1234
+        params: 3 4 5
+        players: human, computer NimAi
+        result: Player 1 wins
+        turns:
+        Take 2 from pile 1
+        Take 4 from pile 3
+
+    with one turn per line, written using each game's Display notation for
+    moves (simultaneous moves are separated by semicolons).
+    The result is for people reading the record; when parsing, it is worked
+    out again from the turns.
+*/
+
+use super::abstract_game::{AbstractGame, GameStatus, Turn};
+use super::history::History;
+use super::player::Player;
+use super::random;
+use super::util;
+
+use std::fmt::{self, Display};
+use std::fs;
+use std::str::FromStr;
+
+/// Who played as a player: a person, or an AI (by name)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerKind {
+    Human,
+    Computer(String),
+}
+impl Display for PlayerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerKind::Human => write!(f, "human"),
+            PlayerKind::Computer(name) => write!(f, "computer {}", name),
+        }
+    }
+}
+impl FromStr for PlayerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().split_once(' ') {
+            None if s.trim() == "human" => Ok(PlayerKind::Human),
+            Some(("computer", name)) => {
+                Ok(PlayerKind::Computer(name.trim().to_string()))
+            }
+            _ => Err(format!("Unknown kind of player: {}. ", s.trim())),
+        }
+    }
+}
+
+pub struct GameRecord<G, const N: usize>
+where
+    G: AbstractGame<N>,
+{
+    pub params: G::SetupParams,
+    pub seed: u64,
+    pub players: Vec<PlayerKind>,
+    pub turns: Vec<Turn<G::Move, N>>,
+}
+
+impl<G, const N: usize> GameRecord<G, N>
+where
+    G: AbstractGame<N> + Clone,
+    G::SetupParams: Clone,
+    G::Move: Clone + Display,
+{
+    /// Record of a game played from the given setup, so far
+    pub fn new(
+        params: &G::SetupParams,
+        seed: u64,
+        players: &[PlayerKind],
+        history: &History<G, N>,
+    ) -> Self {
+        debug_assert_eq!(players.len(), N);
+        let players = players.to_vec();
+        let turns = history.turns().cloned().collect();
+        Self { params: params.clone(), seed, players, turns }
+    }
+
+    /// Starting position of the game
+    pub fn start(&self) -> G {
+        G::game_setup(self.params.clone(), &mut random::seeded(self.seed))
+    }
+
+    /// Replay the recorded turns from the start (assumes they are valid,
+    /// which is checked when parsing a record)
+    pub fn history(&self) -> History<G, N> {
+        let mut history = History::new(self.start());
+        for turn in &self.turns {
+            history.make_turn(turn.clone());
+        }
+        history
+    }
+
+    /// The record as text
+    pub fn to_text(&self) -> String {
+        let players: Vec<String> =
+            self.players.iter().map(|kind| kind.to_string()).collect();
+        let mut result = format!(
+            "game: {}\nseed: {}\nparams: {}\nplayers: {}\nresult: {}\nturns:\n",
+            G::NAME,
+            self.seed,
+            G::format_params(&self.params),
+            players.join(", "),
+            result_line(self.history().game().status()),
+        );
+        for turn in &self.turns {
+            result.push_str(&format!("{}\n", turn));
+        }
+        result
+    }
+
+    /// Parse a record from text, checking that every turn is valid
+    pub fn from_text(raw: &str) -> Result<Self, String> {
+        let mut lines = raw.lines();
+        let headers = headers(&mut lines)?;
+        let name = header(&headers, "game")?;
+        if name != G::NAME {
+            return Err(format!(
+                "This is a record of {}, not {}. ",
+                name,
+                G::NAME
+            ));
+        }
+        let seed = header(&headers, "seed")?
+            .parse()
+            .map_err(|_| "Seed should be an integer. ".to_string())?;
+        let params = G::parse_params(header(&headers, "params")?)?;
+        let players = match header(&headers, "players") {
+            Ok(raw) => raw
+                .split(',')
+                .map(|kind| kind.parse())
+                .collect::<Result<Vec<PlayerKind>, String>>()?,
+            Err(_) => vec![PlayerKind::Human; N],
+        };
+        if players.len() != N {
+            return Err(format!("Expected {} players. ", N));
+        }
+
+        let mut game = G::game_setup(params.clone(), &mut random::seeded(seed));
+        let mut turns = Vec::new();
+        for (i, line) in
+            lines.filter(|line| !line.trim().is_empty()).enumerate()
+        {
+            let turn = parse_turn(&game, line)
+                .map_err(|msg| format!("Turn {} ({}): {}", i + 1, line, msg))?;
+            game.make_turn(turn.clone());
+            turns.push(turn);
+        }
+        Ok(Self { params, seed, players, turns })
+    }
+
+    /// Write the record to a file
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text())
+            .map_err(|err| format!("Could not save to {}: {}. ", path, err))
+    }
+
+    /// Read a record from a file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let raw = fs::read_to_string(path)
+            .map_err(|err| format!("Could not load {}: {}. ", path, err))?;
+        Self::from_text(&raw)
+    }
+}
+
+/// Name of the game a record is for (to find out which game to parse it as)
+pub fn game_name(raw: &str) -> Result<&str, String> {
+    header(&headers(&mut raw.lines())?, "game")
+}
+
+/// Parse the header lines "key: value" up to the "turns:" line
+fn headers<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Result<Vec<(&'a str, &'a str)>, String> {
+    let mut result = Vec::new();
+    for line in lines {
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Expected 'key: value', not {}. ", line))?;
+        if key.trim() == "turns" {
+            return Ok(result);
+        }
+        result.push((key.trim(), value.trim()));
+    }
+    Err("Expected a line 'turns:'. ".to_string())
+}
+
+/// Look up the value of a header
+fn header<'a>(
+    headers: &[(&'a str, &'a str)],
+    key: &str,
+) -> Result<&'a str, String> {
+    headers
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| *v)
+        .ok_or_else(|| format!("Expected a line '{}: ...'. ", key))
+}
+
+/// How the game stands, in one line
+fn result_line<const N: usize>(status: GameStatus<N>) -> String {
+    match status {
+        GameStatus::Won(plyr) => format!("{} wins", plyr),
+        GameStatus::Draw => "draw".to_string(),
+        GameStatus::SharedWin(plyrs) => format!("{} win", plyrs),
+        GameStatus::Ranked(places) => {
+            let places: Vec<String> = Player::<N>::all()
+                .map(|p| {
+                    let place = util::ordinal(places[p.as_index()] + 1);
+                    format!("{} {}", p, place)
+                })
+                .collect();
+            format!("ranked: {}", places.join(", "))
+        }
+        _ => "in progress".to_string(),
+    }
+}
+
+/// Parse a valid turn in the Display format of Turn
+fn parse_turn<G, const N: usize>(
+    game: &G,
+    raw: &str,
+) -> Result<Turn<G::Move, N>, String>
+where
+    G: AbstractGame<N>,
+{
+    match game.status() {
+        GameStatus::Simultaneous(plyrs) => {
+            let mut moves = Vec::new();
+            for part in raw.split(';') {
+                let (name, mv) = part
+                    .split_once(':')
+                    .ok_or_else(|| "Expected 'Player k: move'. ".to_string())?;
+                let plyr: Player<N> = name
+                    .trim()
+                    .trim_start_matches("Player")
+                    .trim()
+                    .parse()
+                    .map_err(|err| format!("Invalid player: {}. ", err))?;
+                if !plyrs.contains(plyr) {
+                    return Err(format!("{} is not to move. ", plyr));
+                }
+                moves.push((plyr, game.parse_valid_move_for(plyr, mv.trim())?));
+            }
+            if moves.len() != plyrs.len() {
+                return Err(format!("Expected moves from {}. ", plyrs));
+            }
+            Ok(Turn::Simultaneous(moves))
+        }
+        status if status.is_ended() => Err("The game is over. ".to_string()),
+        _ => game.parse_valid_move(raw.trim()).map(Turn::Move),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nim::{NimParams, NimState};
+    use crate::pig::PigState;
+    use crate::rps::RpsState;
+
+    fn play_first_moves<G, const N: usize>(
+        params: G::SetupParams,
+        seed: u64,
+        n: usize,
+    ) -> GameRecord<G, N>
+    where
+        G: AbstractGame<N> + Clone,
+        G::SetupParams: Clone,
+        G::Move: Clone + Display,
+    {
+        let mut rng = random::seeded(seed);
+        let mut history = History::new(G::game_setup(params.clone(), &mut rng));
+        for _ in 0..n {
+            let game = history.game();
+            let turn = match game.status() {
+                GameStatus::Simultaneous(plyrs) => Turn::Simultaneous(
+                    plyrs
+                        .iter()
+                        .map(|p| (p, game.legal_moves_for(p).pop().unwrap()))
+                        .collect(),
+                ),
+                GameStatus::Chance => {
+                    Turn::Move(game.sample_chance_move(&mut rng))
+                }
+                _ => Turn::Move(game.legal_moves().pop().unwrap()),
+            };
+            history.make_turn(turn);
+        }
+        GameRecord::new(&params, seed, &vec![PlayerKind::Human; N], &history)
+    }
+
+    fn assert_round_trip<G, const N: usize>(record: &GameRecord<G, N>)
+    where
+        G: AbstractGame<N> + Clone,
+        G::SetupParams: Clone,
+        G::Move: Clone + Display,
+    {
+        let text = record.to_text();
+        let parsed = GameRecord::<G, N>::from_text(&text).unwrap();
+        assert_eq!(parsed.seed, record.seed);
+        assert_eq!(parsed.players, record.players);
+        assert!(parsed.turns == record.turns);
+        assert_eq!(parsed.to_text(), text);
+    }
+
+    #[test]
+    fn test_nim_round_trip() {
+        let record = play_first_moves::<NimState<2>, 2>(
+            NimParams::new(vec![3, 4, 5]),
+            0,
+            3,
+        );
+        assert_eq!(record.turns.len(), 3);
+        assert!(record.to_text().starts_with(
+            "game: nim\nseed: 0\nparams: 3 4 5\nplayers: human, human\n\
+            result: Player 1 wins\nturns:\n"
+        ));
+        assert_round_trip(&record);
+    }
+
+    #[test]
+    fn test_pig_round_trip() {
+        assert_round_trip(&play_first_moves::<PigState<3>, 3>(20, 42, 10));
+    }
+
+    #[test]
+    fn test_rps_round_trip() {
+        assert_round_trip(&play_first_moves::<RpsState, 2>(3, 1, 2));
+    }
+
+    #[test]
+    fn test_players() {
+        let raw =
+            "game: nim\nseed: 0\nparams: 3\nplayers: human, computer NimAi\n\
+            turns:\n1 3\n";
+        let record = GameRecord::<NimState<2>, 2>::from_text(raw).unwrap();
+        assert_eq!(
+            record.players,
+            vec![PlayerKind::Human, PlayerKind::Computer("NimAi".to_string())]
+        );
+        assert_eq!(game_name(raw), Ok("nim"));
+        assert!(record.to_text().contains("result: Player 1 wins\n"));
+    }
+
+    #[test]
+    fn test_invalid_record() {
+        let wrong_game = "game: pig\nseed: 0\nparams: 3\nturns:\n";
+        assert!(GameRecord::<NimState<2>, 2>::from_text(wrong_game).is_err());
+        let invalid_move = "game: nim\nseed: 0\nparams: 3\nturns:\n1 4\n";
+        assert!(GameRecord::<NimState<2>, 2>::from_text(invalid_move).is_err());
+        let valid = "game: nim\nseed: 0\nparams: 3\nturns:\n1 2\n";
+        let record = GameRecord::<NimState<2>, 2>::from_text(valid).unwrap();
+        assert_eq!(record.history().game().legal_moves().len(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+/*
+    Implementation of rock-paper-scissors, played over a fixed number of
+    rounds
+
+    Each round, both players choose simultaneously. Whoever wins more
+    rounds wins the game; if they win the same number, it's a draw.
+*/
+
+use crate::abstract_game::{AbstractGame, GameStatus};
+use crate::console::Console;
+use crate::player::{PlayerSet, TwoPlayers};
+use crate::random::GameRng;
+use crate::util;
+
+use std::fmt::{self, Display};
+
+const NUM_PLAYERS: usize = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Hand {
+    Rock,
+    Paper,
+    Scissors,
+}
+impl Hand {
+    const ALL: [Hand; 3] = [Hand::Rock, Hand::Paper, Hand::Scissors];
+
+    pub fn beats(self, other: Hand) -> bool {
+        matches!(
+            (self, other),
+            (Hand::Rock, Hand::Scissors)
+                | (Hand::Paper, Hand::Rock)
+                | (Hand::Scissors, Hand::Paper)
+        )
+    }
+}
+impl Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Hand::Rock => write!(f, "rock"),
+            Hand::Paper => write!(f, "paper"),
+            Hand::Scissors => write!(f, "scissors"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpsState {
+    rounds: usize,
+    played: usize,
+    wins: [usize; NUM_PLAYERS],
+    last_round: Option<[Hand; NUM_PLAYERS]>,
+}
+
+impl AbstractGame<NUM_PLAYERS> for RpsState {
+    const NAME: &'static str = "rps";
+
+    type Move = Hand;
+    type Observation = Self;
+    type SetupParams = usize;
+
+    fn setup_from_user_input(console: &mut dyn Console) -> usize {
+        util::from_user_input_satisfying(
+            console,
+            "Number of rounds? ",
+            "Type a positive integer. ",
+            "Type a positive integer. ",
+            |&rounds| rounds > 0,
+        )
+    }
+
+    fn format_params(rounds: &usize) -> String {
+        rounds.to_string()
+    }
+
+    fn parse_params(raw: &str) -> Result<usize, String> {
+        raw.trim()
+            .parse()
+            .map_err(|_| "Number of rounds should be an integer. ".to_string())
+    }
+
+    fn game_setup(rounds: usize, _rng: &mut GameRng) -> Self {
+        Self { rounds, played: 0, wins: [0; NUM_PLAYERS], last_round: None }
+    }
+
+    fn status(&self) -> GameStatus<NUM_PLAYERS> {
+        if self.played < self.rounds {
+            GameStatus::Simultaneous(PlayerSet::all())
+        } else if self.wins[0] > self.wins[1] {
+            GameStatus::Won(TwoPlayers::ONE)
+        } else if self.wins[1] > self.wins[0] {
+            GameStatus::Won(TwoPlayers::TWO)
+        } else {
+            GameStatus::Draw
+        }
+    }
+
+    fn query(&self) -> String {
+        "Rock, paper, or scissors? ".to_string()
+    }
+
+    fn parse_move(&self, raw: &str) -> Result<Hand, String> {
+        match raw.trim().to_lowercase().as_str() {
+            "rock" | "r" => Ok(Hand::Rock),
+            "paper" | "p" => Ok(Hand::Paper),
+            "scissors" | "s" => Ok(Hand::Scissors),
+            _ => Err("Type rock, paper, or scissors. ".to_string()),
+        }
+    }
+
+    fn check_move(&self, _mv: &Hand) -> Result<(), String> {
+        if self.is_ended() {
+            Err("The game is over. ".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<Hand> {
+        if self.is_ended() {
+            Vec::new()
+        } else {
+            Hand::ALL.to_vec()
+        }
+    }
+
+    fn make_move(&mut self, _mv: Hand) {
+        unreachable!("moves in rock-paper-scissors are simultaneous")
+    }
+
+    fn make_simultaneous_moves(&mut self, moves: Vec<(TwoPlayers, Hand)>) {
+        debug_assert_eq!(moves.len(), NUM_PLAYERS);
+        let mut hands = [Hand::Rock; NUM_PLAYERS];
+        for (plyr, hand) in moves {
+            hands[plyr.as_index()] = hand;
+        }
+        if hands[0].beats(hands[1]) {
+            self.wins[0] += 1;
+        } else if hands[1].beats(hands[0]) {
+            self.wins[1] += 1;
+        }
+        self.played += 1;
+        self.last_round = Some(hands);
+    }
+
+    fn print_state_visible(&self, _plyr: TwoPlayers) -> String {
+        let mut result = format!(
+            "Rounds played: {} of {}\nRounds won: {} {}, {} {}",
+            self.played,
+            self.rounds,
+            TwoPlayers::ONE,
+            self.wins[0],
+            TwoPlayers::TWO,
+            self.wins[1],
+        );
+        if let Some([hand1, hand2]) = self.last_round {
+            result.push_str(&format!(
+                "\nLast round: {} played {}, {} played {}",
+                TwoPlayers::ONE,
+                hand1,
+                TwoPlayers::TWO,
+                hand2,
+            ));
+        }
+        result
+    }
+
+    fn observe(&self, _plyr: TwoPlayers) -> Self {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_game::Turn;
+    use crate::random;
+
+    fn play_round(game: &mut RpsState, hand1: Hand, hand2: Hand) {
+        let moves = vec![(TwoPlayers::ONE, hand1), (TwoPlayers::TWO, hand2)];
+        game.make_turn(Turn::Simultaneous(moves));
+    }
+
+    #[test]
+    fn test_rounds() {
+        let mut game = RpsState::game_setup(3, &mut random::seeded(0));
+        assert_eq!(game.status(), GameStatus::Simultaneous(PlayerSet::all()));
+        assert_eq!(game.cur_player(), None);
+        play_round(&mut game, Hand::Rock, Hand::Scissors);
+        play_round(&mut game, Hand::Rock, Hand::Rock);
+        assert_eq!(game.wins, [1, 0]);
+        play_round(&mut game, Hand::Rock, Hand::Paper);
+        assert_eq!(game.status(), GameStatus::Draw);
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_winner() {
+        let mut game = RpsState::game_setup(1, &mut random::seeded(0));
+        play_round(&mut game, Hand::Scissors, Hand::Rock);
+        assert_eq!(game.status(), GameStatus::Won(TwoPlayers::TWO));
+    }
+}
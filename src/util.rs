@@ -1,45 +1,49 @@
 /*
     Utility
 
-    - Getting user input from stdin
+    - Getting user input from a console
 
-    - String parsing functions
+    - String parsing and formatting functions
 */
 
-use std::io::{self, BufRead, Write};
+use super::console::Console;
+
 use std::str::FromStr;
 
-pub fn user_input(query: &str) -> String {
-    print!("{}", query);
-    io::stdout().flush().expect("failed to flush stdout");
-    let stdin = io::stdin();
-    let mut line_iter = stdin.lock().lines();
-    line_iter
-        .next()
-        .expect("failed to get line from stdin")
-        .expect("failed to get line from stdin")
+pub fn user_input(console: &mut dyn Console, query: &str) -> String {
+    console.print(query);
+    console.read_line()
 }
 
-pub fn from_user_input_parsing<T, P>(query: &str, parse_fun: P) -> T
+pub fn from_user_input_parsing<T, P>(
+    console: &mut dyn Console,
+    query: &str,
+    parse_fun: P,
+) -> T
 where
     P: Fn(String) -> Result<T, String>,
 {
-    let mut result = parse_fun(user_input(query));
+    let mut result = parse_fun(user_input(console, query));
     while let Err(msg) = result {
-        result = parse_fun(user_input(&msg));
+        result = parse_fun(user_input(console, &msg));
     }
     result.unwrap()
 }
 
-pub fn from_user_input<T: FromStr>(query: &str, query_again: &str) -> T {
-    let mut result = user_input(query).parse().ok();
+pub fn from_user_input<T: FromStr>(
+    console: &mut dyn Console,
+    query: &str,
+    query_again: &str,
+) -> T {
+    let mut result = user_input(console, query).parse().ok();
     while result.is_none() {
-        result = user_input(query_again).parse().ok();
+        result = user_input(console, query_again).parse().ok();
     }
     result.unwrap()
 }
 
 pub fn from_user_input_satisfying<T, F>(
+    console: &mut dyn Console,
     query: &str,
     query_again: &str,
     query_invalid: &str,
@@ -49,9 +53,9 @@ where
     T: FromStr,
     F: Fn(&T) -> bool,
 {
-    let mut result = from_user_input(query, query_again);
+    let mut result = from_user_input(console, query, query_again);
     while !predicate(&result) {
-        result = from_user_input(query_invalid, query_again);
+        result = from_user_input(console, query_invalid, query_again);
     }
     result
 }
@@ -70,3 +74,15 @@ pub fn parse_vec_usize(raw: &str) -> Option<Vec<usize>> {
 pub fn parse_vec_isize(raw: &str) -> Option<Vec<isize>> {
     parse_vec(raw)
 }
+
+/// Ordinal for a positive integer: "1st", "2nd", "3rd", "4th", ...
+pub fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (1, 11) | (2, 12) | (3, 13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}